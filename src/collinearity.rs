@@ -0,0 +1,194 @@
+//! Diagnostics for collinearity in the design matrix
+use crate::{error::RegressionResult, num::Float};
+use ndarray::{s, Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_linalg::{Eigh, UPLO};
+
+/// Collinearity diagnostics of a design matrix: the variance inflation factor of each covariate,
+/// and the condition indices and variance-decomposition proportions of Belsley, Kuh and Welsch.
+#[derive(Clone, Debug)]
+pub struct Collinearity<F> {
+    /// The variance inflation factor of each covariate, not including the intercept. This is the
+    /// factor by which the variance of the coefficient is inflated relative to a design in which
+    /// the covariate is orthogonal to all others. When the model has an intercept the covariates
+    /// are centered first; otherwise these are the uncentered factors. Exactly collinear
+    /// covariates have factors on the order of the inverse of machine epsilon.
+    pub vif: Array1<F>,
+    /// The condition number of the design with each column scaled to unit length, which is the
+    /// largest of the condition indices. Values above about 30 indicate harmful collinearity.
+    pub condition_number: F,
+    /// The condition index of each principal component of the scaled design, in increasing
+    /// order. The index of a component is the square root of the ratio of the largest eigenvalue
+    /// of `X^T * X` to the eigenvalue of the component.
+    pub condition_indices: Array1<F>,
+    /// The proportion of the variance of each parameter associated with each component, with
+    /// one row per component in the order of `condition_indices` and one column per parameter,
+    /// including the intercept. Each column sums to one.
+    pub var_decomp: Array2<F>,
+}
+
+impl<F> Collinearity<F>
+where
+    F: Float,
+{
+    /// Compute the diagnostics from a design matrix whose first column is the intercept if
+    /// `use_intercept` is true.
+    pub(crate) fn from_design<S>(
+        design: &ArrayBase<S, Ix2>,
+        use_intercept: bool,
+    ) -> RegressionResult<Self>
+    where
+        S: Data<Elem = F>,
+    {
+        let (lambda, vecs) = scaled_eigh(design.to_owned())?;
+        let lambda_max: F = lambda.first().copied().unwrap_or_else(F::one);
+        let lambda = floor_eigenvalues(lambda, lambda_max);
+        let condition_indices: Array1<F> = lambda.mapv(|l| num_traits::Float::sqrt(lambda_max / l));
+        let condition_number: F = condition_indices.last().copied().unwrap_or_else(F::one);
+        // phi[[j, k]] = v_jk^2 / lambda_k is the contribution of component k to the variance of
+        // parameter j.
+        let phi: Array2<F> = vecs.mapv(|v| v * v) / &lambda;
+        let total: Array1<F> = phi.sum_axis(Axis(1));
+        let var_decomp: Array2<F> = (phi / &total.insert_axis(Axis(1))).reversed_axes();
+
+        let start = usize::from(use_intercept);
+        let mut covariates: Array2<F> = design.slice(s![.., start..]).to_owned();
+        if use_intercept {
+            if let Some(mean) = covariates.mean_axis(Axis(0)) {
+                covariates -= &mean;
+            }
+        }
+        let (lambda, vecs) = scaled_eigh(covariates)?;
+        let lambda_max: F = lambda.first().copied().unwrap_or_else(F::one);
+        let lambda = floor_eigenvalues(lambda, lambda_max);
+        // With unit-length columns X^T * X is the correlation matrix, and the diagonal of its
+        // inverse gives the variance inflation factors.
+        let vif: Array1<F> = (vecs.mapv(|v| v * v) / &lambda).sum_axis(Axis(1));
+
+        Ok(Self {
+            vif,
+            condition_number,
+            condition_indices,
+            var_decomp,
+        })
+    }
+
+    /// Return the sets of parameters involved in each near-dependency of the design, following
+    /// the rule of Belsley, Kuh and Welsch: for each component with a condition index above
+    /// `index_threshold` (commonly 30), the parameters having more than `prop_threshold`
+    /// (commonly 0.5) of their variance associated with that component. Parameters are indexed
+    /// as in the fit result, with the intercept first if it is used.
+    pub fn collinear_sets(&self, index_threshold: F, prop_threshold: F) -> Vec<Vec<usize>> {
+        self.condition_indices
+            .iter()
+            .zip(self.var_decomp.rows())
+            .filter(|(&index, _)| index > index_threshold)
+            .map(|(_, props)| {
+                props
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &p)| p > prop_threshold)
+                    .map(|(j, _)| j)
+                    .collect::<Vec<usize>>()
+            })
+            .filter(|set| !set.is_empty())
+            .collect()
+    }
+}
+
+/// Scale each column of the matrix to unit length and return the eigenvalues of its Gram matrix
+/// in decreasing order along with the corresponding eigenvectors as columns. Columns of zeros
+/// are left unscaled.
+fn scaled_eigh<F>(mut x: Array2<F>) -> RegressionResult<(Array1<F>, Array2<F>)>
+where
+    F: Float,
+{
+    for mut col in x.columns_mut() {
+        let norm = num_traits::Float::sqrt(col.dot(&col));
+        if norm > F::zero() {
+            col.mapv_inplace(|v| v / norm);
+        }
+    }
+    let xtx: Array2<F> = x.t().dot(&x);
+    let (vals, vecs) = xtx.eigh(UPLO::Lower)?;
+    // Round-off can leave the eigenvalues of a singular matrix slightly negative.
+    let vals: Array1<F> = vals
+        .iter()
+        .rev()
+        .map(|&v| num_traits::Float::max(F::from(v).unwrap(), F::zero()))
+        .collect();
+    let vecs: Array2<F> = vecs.slice(s![.., ..;-1]).to_owned();
+    Ok((vals, vecs))
+}
+
+/// Raise the eigenvalues to at least machine epsilon relative to the largest, so that exactly
+/// singular directions give large but finite diagnostics.
+fn floor_eigenvalues<F>(lambda: Array1<F>, lambda_max: F) -> Array1<F>
+where
+    F: Float,
+{
+    let floor = lambda_max * F::epsilon();
+    lambda.mapv_into(|l| num_traits::Float::max(l, floor))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Linear};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array1, Array2};
+
+    #[test]
+    fn vif_regression() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![
+            [0.6, 0.2, 1.1],
+            [2.1, 0.4, 0.3],
+            [0.4, -0.3, -0.5],
+            [-3.2, 0.1, 0.9],
+            [0.7, 0.6, 0.2],
+            [0.1, -0.1, -1.2],
+            [-0.3, 0.5, 0.4],
+            [0.5, 0.0, 0.7]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let diag = model.collinearity()?;
+        // The VIF of each covariate is 1 / (1 - R^2) for its regression on the other covariates.
+        for j in 0..3 {
+            let others: Vec<usize> = (0..3).filter(|&k| k != j).collect();
+            let other_x: Array2<f64> = data_x.select(ndarray::Axis(1), &others);
+            let col_y: Array1<f64> = data_x.column(j).to_owned();
+            let aux = ModelBuilder::<Linear>::data(&col_y, &other_x).build()?;
+            let r_sq = aux.fit()?.r_sq();
+            assert_abs_diff_eq!(diag.vif[j], 1. / (1. - r_sq), epsilon = 1e-10);
+        }
+        assert_eq!(diag.condition_indices.len(), 4);
+        assert_abs_diff_eq!(diag.condition_indices[0], 1., epsilon = 1e-12);
+        assert_abs_diff_eq!(diag.condition_number, diag.condition_indices[3]);
+        for col in diag.var_decomp.columns() {
+            assert_abs_diff_eq!(col.sum(), 1., epsilon = 1e-12);
+        }
+        assert!(diag.collinear_sets(30., 0.5).is_empty());
+        Ok(())
+    }
+
+    /// The diagnostics identify the columns responsible when the build fails.
+    #[test]
+    fn collinear_columns() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4];
+        let data_x = array![
+            [0.5, 0.25, 0.75],
+            [2., 0.5, 2.5],
+            [0.5, -0.25, 0.25],
+            [-3., 0., -3.],
+            [0.75, 0.5, 1.25],
+            [0., -0.5, -0.5]
+        ];
+        let builder = ModelBuilder::<Linear>::data(&data_y, &data_x);
+        let diag = builder.collinearity()?;
+        assert!(builder.colinear_tol(1e-10).build().is_err());
+        assert!(diag.vif.iter().all(|&v| v > 1e10));
+        assert!(diag.condition_number > 1e6);
+        assert_eq!(diag.collinear_sets(30., 0.5), vec![vec![1, 2, 3]]);
+        Ok(())
+    }
+}
@@ -1,20 +1,32 @@
 //! Stores the fit results of the IRLS regression and provides functions that
 //! depend on the MLE estimate. These include statistical tests for goodness-of-fit.
 
+pub mod bootstrap;
+pub mod classification;
+pub mod cv;
+pub mod gof;
+pub mod hypothesis;
+pub mod influence;
+pub mod jackknife;
+pub mod margins;
 pub mod options;
+pub mod path;
 use crate::{
-    error::RegressionResult,
+    error::{RegressionError, RegressionResult},
     glm::{DispersionType, Glm},
     irls::Irls,
-    link::{Link, Transform},
+    link::{ExpCoefKind, Link, Transform},
+    math::{kkt_matrix, norm_quantile, t_quantile},
     model::{Dataset, Model},
     num::Float,
     regularization::IrlsReg,
+    utility::{one_pad, Standardization},
     Linear,
 };
-use ndarray::{array, Array1, Array2, ArrayBase, ArrayView1, Axis, Data, Ix2};
+use ndarray::{array, s, Array1, Array2, ArrayBase, ArrayView1, Axis, Data, Ix2};
 use ndarray_linalg::InverseInto;
 use options::FitOptions;
+use rand::Rng;
 use std::{
     cell::{Ref, RefCell},
     marker::PhantomData,
@@ -31,6 +43,10 @@ where
     data: &'a Dataset<F>,
     /// Whether the intercept covariate is used
     use_intercept: bool,
+    /// The standardization of the covariates applied by the model, if any
+    standardization: Option<&'a Standardization<F>>,
+    /// The columns of the full design matrix that were dropped as aliased
+    aliased: &'a [usize],
     /// The parameter values that maximize the likelihood as given by the IRLS regression.
     pub result: Array1<F>,
     /// The options used for this fit.
@@ -53,20 +69,113 @@ where
     null_model: RefCell<Option<(F, Array1<F>)>>,
 }
 
+/// The scale on which predictions are returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PredictType {
+    /// The linear predictor, `eta = X * beta` plus any offset.
+    Link,
+    /// The expected value of the response, `mu = g^-1(eta)`.
+    Response,
+}
+
+/// Predictions of a fit along with their uncertainties, as returned by
+/// `Fit::predict_with_se()`.
+#[derive(Clone, Debug)]
+pub struct Prediction<F> {
+    /// The linear predictor for each observation.
+    pub lin_pred: Array1<F>,
+    /// The standard error of the linear predictor, from the parameter covariance matrix.
+    pub lin_pred_se: Array1<F>,
+    /// The expected value of the response, i.e. the inverse link function of the linear
+    /// predictor.
+    pub mean: Array1<F>,
+    /// The standard error of the expected response by the delta method.
+    pub mean_se: Array1<F>,
+    /// The lower bound of the confidence interval of the expected response.
+    pub lower: Array1<F>,
+    /// The upper bound of the confidence interval of the expected response.
+    pub upper: Array1<F>,
+}
+
+/// The exponentiated coefficients of a fit with their confidence intervals, as returned by
+/// `Fit::exp_coef()`. Their interpretation, e.g. as odds ratios, is determined by the link
+/// function.
+#[derive(Clone, Debug)]
+pub struct ExpCoefficients<F> {
+    /// The interpretation of the exponentiated coefficients.
+    pub kind: ExpCoefKind,
+    /// The exponentiated coefficients, `exp(beta)`.
+    pub values: Array1<F>,
+    /// The lower bound of the confidence interval of each exponentiated coefficient.
+    pub lower: Array1<F>,
+    /// The upper bound of the confidence interval of each exponentiated coefficient.
+    pub upper: Array1<F>,
+}
+
+/// Pseudo-R^2 measures of the quality of a fit, as returned by `Fit::pseudo_r_sq()`. These
+/// compare the fit to the null model and generalize the coefficient of determination to models
+/// without a natural sum of squares.
+#[derive(Clone, Debug)]
+pub struct PseudoRSq<F> {
+    /// McFadden's measure, `1 - L / L_0`, where `L` and `L_0` are the log-likelihoods of the fit
+    /// and the null model.
+    pub mcfadden: F,
+    /// McFadden's measure adjusted for the number of parameters `k`, `1 - (L - k) / L_0`. Under
+    /// regularization `k` is the effective number of parameters `edf()`, as in the AIC. Like the
+    /// adjusted R^2, this can be negative for a poor fit.
+    pub mcfadden_adj: F,
+    /// The Cox-Snell measure, `1 - exp(2 * (L_0 - L) / n)`. It is a function of the
+    /// likelihood-ratio statistic, so it does not depend on the normalization of the likelihood
+    /// when the dispersion is fixed.
+    pub cox_snell: F,
+    /// Nagelkerke's measure, the Cox-Snell measure divided by its maximum possible value
+    /// `1 - exp(2 * L_0 / n)`.
+    pub nagelkerke: F,
+    /// Efron's measure, one minus the ratio of the sum of squared response residuals to the total
+    /// sum of squares about the mean response.
+    pub efron: F,
+}
+
 impl<'a, M, F> Fit<'a, M, F>
 where
     M: Glm,
     F: 'static + Float,
 {
-    /// Returns the Akaike information criterion for the model fit.
-    // TODO: Should an effective number of parameters that takes regularization
-    // into acount be considered?
+    /// Returns the indices of the parameters, as in `result`, that are fixed at a lower or upper
+    /// bound given by `FitConfig::bounds()`. This is empty if the fit has no bounds.
+    pub fn active_bounds(&self) -> Vec<usize> {
+        let (lower, upper) = match (&self.options.lower_bounds, &self.options.upper_bounds) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            _ => return Vec::new(),
+        };
+        let start = usize::from(self.use_intercept);
+        self.kept_columns()
+            .iter()
+            .zip(self.result.iter())
+            .enumerate()
+            .filter(|(_, (&col, &b))| {
+                col >= start && (b == lower[col - start] || b == upper[col - start])
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the Akaike information criterion for the model fit. Under regularization, the
+    /// number of parameters is replaced by the effective degrees of freedom `edf()` and the
+    /// likelihood of the data is used without the penalty.
     pub fn aic(&self) -> F {
-        F::from(2 * self.n_par).unwrap() - F::from(2.).unwrap() * self.model_like
+        F::from(2.).unwrap() * (self.edf() - self.data_like())
+    }
+
+    /// Returns the indices of the columns of the full design matrix, including the intercept,
+    /// that were dropped because they are linearly dependent on earlier columns. This is empty
+    /// unless the model was built with `drop_aliased()`.
+    pub fn aliased(&self) -> &[usize] {
+        self.aliased
     }
 
-    /// Returns the Bayesian information criterion for the model fit.
-    // TODO: Also consider the effect of regularization on this statistic.
+    /// Returns the Bayesian information criterion for the model fit. As with `aic()`, the effective
+    /// degrees of freedom and the unpenalized likelihood are used under regularization.
     // TODO: Wikipedia suggests that the variance should included in the number
     // of parameters for multiple linear regression. Should an additional
     // parameter be included for the dispersion parameter? This question does
@@ -74,13 +183,26 @@ where
     // this package.
     pub fn bic(&self) -> F {
         let logn = num_traits::Float::ln(F::from(self.data.y.len()).unwrap());
-        logn * F::from(self.n_par).unwrap() - F::from(2.).unwrap() * self.model_like
+        logn * self.edf() - F::from(2.).unwrap() * self.data_like()
+    }
+
+    /// Returns the parameters with one entry for each column of the full design matrix, including
+    /// the intercept, where the columns dropped as aliased are NaN. This is the same as `result`
+    /// if no columns were dropped.
+    pub fn coefficients(&self) -> Array1<F> {
+        let mut coef = Array1::<F>::from_elem(self.n_par + self.aliased.len(), F::nan());
+        for (j, &val) in self.kept_columns().iter().zip(self.result.iter()) {
+            coef[*j] = val;
+        }
+        coef
     }
 
     /// The covariance matrix estimated by the Fisher information and the dispersion parameter (for
-    /// families with a free scale). The matrix is cached to avoid repeating the potentially
-    /// expensive matrix inversion.
-    pub fn covariance(&self) -> RegressionResult<Ref<Array2<F>>> {
+    /// families with a free scale). Under linear equality constraints the inverse of the Fisher
+    /// information is restricted to the null space of the constraints. The parameters held at a
+    /// bound (see `active_bounds()`) are not removed, so their variances do not reflect the
+    /// bound. The matrix is cached to avoid repeating the potentially expensive matrix inversion.
+    pub fn covariance(&self) -> RegressionResult<Ref<'_, Array2<F>>> {
         if self.cov.borrow().is_none() {
             if self.data.weights.is_some() {
                 // NOTE: Perhaps it is just the fisher matrix that must be updated.
@@ -94,20 +216,28 @@ where
             // For linear/gamma regression it is estimated from the data.
             let phi: F = self.dispersion();
             // NOTE: invh/invh_into() are bugged and incorrect!
-            let unscaled_cov: Array2<F> = fisher_reg.inv_into()?;
+            let unscaled_cov: Array2<F> = match &self.options.constraint_mat {
+                Some(c_mat) => kkt_matrix(&fisher_reg, c_mat)
+                    .inv_into()?
+                    .slice_move(s![..self.n_par, ..self.n_par]),
+                None => fisher_reg.inv_into()?,
+            };
             let cov = unscaled_cov * phi;
             *self.cov.borrow_mut() = Some(cov);
         }
         Ok(Ref::map(self.cov.borrow(), |x| x.as_ref().unwrap()))
     }
 
+    /// The log-likelihood of the data at the fit result, without any regularization penalty.
+    fn data_like(&self) -> F {
+        self.model_like - self.reg.likelihood(&self.result)
+    }
+
     /// Returns the deviance of the fit: twice the difference between the
     /// saturated likelihood and the model likelihood. Asymptotically this fits
     /// a chi-squared distribution with `self.ndf()` degrees of freedom.
-    /// Note that the regularized likelihood is used here.
-    // TODO: This is likely sensitive to regularization because the saturated
-    // model is not regularized but the model likelihood is. Perhaps this can be
-    // accounted for with an effective number of degrees of freedom.
+    /// Note that the regularized likelihood is used here, and that `ndf()` uses the
+    /// effective degrees of freedom under regularization.
     pub fn deviance(&self) -> F {
         // Note that this must change if the GLM likelihood subtracts the
         // saturated one already.
@@ -125,7 +255,7 @@ where
         use DispersionType::*;
         match M::DISPERSED {
             FreeDispersion => {
-                let ndf: F = F::from(self.ndf()).unwrap();
+                let ndf: F = self.ndf();
                 let dev = self.deviance();
                 dev / ndf
             }
@@ -133,10 +263,78 @@ where
         }
     }
 
+    /// Returns the effective number of degrees of freedom used by the fit. This is the number of
+    /// parameters for an unregularized fit. Under L2 regularization it is the trace of the ridge
+    /// hat matrix, `tr[(I + L2)^-1 * I]` with the unpenalized Fisher information `I`; under L1
+    /// regularization it is the number of non-zero parameters; and under elastic net
+    /// regularization it is the trace of the ridge hat matrix restricted to the non-zero
+    /// parameters. Each linear equality constraint given by `FitConfig::constraints()` removes
+    /// one degree of freedom, which is exact for an unregularized fit.
+    pub fn edf(&self) -> F {
+        let edf = self.reg.edf(&self.fisher_data(&self.result), &self.result);
+        let n_cons = self
+            .options
+            .constraint_mat
+            .as_ref()
+            .map_or(0, |c_mat| c_mat.nrows());
+        num_traits::Float::max(edf - F::from(n_cons).unwrap(), F::zero())
+    }
+
+    /// Returns the design matrix for new covariate data, applying the same standardization and
+    /// intercept padding that the model applied to the training data. Returns an error if the
+    /// number of covariates is not consistent with the training data.
+    pub fn design_matrix<S>(&self, data_x: &ArrayBase<S, Ix2>) -> RegressionResult<Array2<F>>
+    where
+        S: Data<Elem = F>,
+    {
+        let n_cols = self.n_par + self.aliased.len();
+        let n_cov = if self.use_intercept {
+            n_cols - 1
+        } else {
+            n_cols
+        };
+        if data_x.ncols() != n_cov {
+            return Err(RegressionError::BadInput(format!(
+                "Expected {n_cov} covariates, found {}",
+                data_x.ncols()
+            )));
+        }
+        let data_x: Array2<F> = match self.standardization {
+            Some(stand) => stand.transform(data_x),
+            None => data_x.to_owned(),
+        };
+        let design: Array2<F> = if self.use_intercept {
+            one_pad(data_x.view())
+        } else {
+            data_x
+        };
+        Ok(if self.aliased.is_empty() {
+            design
+        } else {
+            design.select(Axis(1), &self.kept_columns())
+        })
+    }
+
     /// Returns the errors in the response variables for the data passed as an
     /// argument given the current model fit.
     fn errors(&self, data: &Dataset<F>) -> Array1<F> {
-        &data.y - &self.predict(&data.x, data.linear_offset.as_ref())
+        &data.y - &self.predict_design(&data.x, data.linear_offset.as_ref())
+    }
+
+    /// Returns the exponentiated coefficients `exp(beta)` with confidence intervals at the given
+    /// level (e.g. 0.95), which are the exponentiated Wald intervals of the coefficients. These
+    /// are labeled according to the link function: odds ratios for logit links and incidence rate
+    /// ratios for the log link of Poisson regression. The intercept is included if it is used.
+    pub fn exp_coef(&self, level: F) -> RegressionResult<ExpCoefficients<F>> {
+        let z: F = F::from(norm_quantile(two_sided_quantile(level)?)).unwrap();
+        let std_err: Array1<F> = self.covariance()?.diag().mapv(num_traits::Float::sqrt);
+        let exp = |beta: Array1<F>| beta.mapv_into(num_traits::Float::exp);
+        Ok(ExpCoefficients {
+            kind: M::Link::EXP_COEF,
+            values: exp(self.result.clone()),
+            lower: exp(&self.result - &(&std_err * z)),
+            upper: exp(&self.result + &(std_err * z)),
+        })
     }
 
     #[deprecated(since = "0.0.10", note = "use predict() instead")]
@@ -148,12 +346,19 @@ where
     where
         S: Data<Elem = F>,
     {
-        self.predict(data_x, lin_off)
+        self.predict_design(data_x, lin_off)
     }
 
     /// Returns the fisher information (the negative hessian of the likelihood)
     /// at the parameter values given. The regularization is included.
     pub fn fisher(&self, params: &Array1<F>) -> Array2<F> {
+        let fisher: Array2<F> = self.fisher_data(params);
+        // Regularize the fisher matrix
+        self.reg.as_ref().irls_mat(fisher, params)
+    }
+
+    /// The Fisher information of the data alone, without the effect of regularization.
+    fn fisher_data(&self, params: &Array1<F>) -> Array2<F> {
         let lin_pred: Array1<F> = self.data.linear_predictor(params);
         let mu: Array1<F> = M::mean(&lin_pred);
         let var_diag: Array1<F> = mu.mapv_into(M::variance);
@@ -161,9 +366,14 @@ where
         let eta_d = M::Link::d_nat_param(&lin_pred);
         let adj_var: Array1<F> = &eta_d * &var_diag * eta_d;
         // calculate the fisher matrix
-        let fisher: Array2<F> = (&self.data.x.t() * &adj_var).dot(&self.data.x);
-        // Regularize the fisher matrix
-        self.reg.as_ref().irls_mat(fisher, params)
+        (&self.data.x.t() * &adj_var).dot(&self.data.x)
+    }
+
+    /// The indices of the columns of the full design matrix that are used in the fit.
+    pub(crate) fn kept_columns(&self) -> Vec<usize> {
+        (0..self.n_par + self.aliased.len())
+            .filter(|j| !self.aliased.contains(j))
+            .collect()
     }
 
     /// Perform a likelihood-ratio test, returning the statistic -2*ln(L_0/L)
@@ -172,14 +382,11 @@ where
     /// The number of degrees of freedom of this statistic, equal to the number
     /// of parameters fixed to zero to form the null model, is `test_ndf()`. By
     /// Wilks' theorem this statistic is asymptotically chi-squared distributed
-    /// with this number of degrees of freedom.
-    // TODO: Should the effective number of degrees of freedom due to
-    // regularization be taken into account? Should the degrees of freedom be a
-    // float?
+    /// with this number of degrees of freedom. Under regularization this is the
+    /// effective number of parameters, which need not be an integer.
     pub fn lr_test(&self) -> F {
-        // The model likelihood should include regularization terms and there
-        // shouldn't be any in the null model with all non-intercept parameters
-        // set to zero.
+        // The model likelihood includes the regularization terms, as does the likelihood of the
+        // null model with all non-intercept parameters set to zero.
         let null_like = self.null_like();
         F::from(-2.).unwrap() * (null_like - self.model_like)
     }
@@ -195,14 +402,15 @@ where
     }
 
     /// Returns the residual degrees of freedom in the model, i.e. the number
-    /// of data points minus the number of parameters. Not to be confused with
-    /// `test_ndf()`, the degrees of freedom in the statistical tests of the
-    /// fit.
-    pub fn ndf(&self) -> usize {
-        self.n_data - self.n_par
+    /// of data points minus the effective number of parameters `edf()`. Not to
+    /// be confused with `test_ndf()`, the degrees of freedom in the statistical
+    /// tests of the fit.
+    pub fn ndf(&self) -> F {
+        F::from(self.n_data).unwrap() - self.edf()
     }
 
-    pub(crate) fn new(data: &'a Dataset<F>, use_intercept: bool, irls: Irls<M, F>) -> Self {
+    pub(crate) fn new(model: &'a Model<M, F>, irls: Irls<M, F>) -> Self {
+        let data = &model.data;
         let Irls {
             guess: result,
             options,
@@ -219,7 +427,9 @@ where
         Self {
             model: PhantomData,
             data,
-            use_intercept,
+            use_intercept: model.use_intercept,
+            standardization: model.standardization.as_ref(),
+            aliased: &model.aliased,
             result,
             options,
             model_like,
@@ -234,7 +444,9 @@ where
 
     /// Returns the likelihood given the null model, which fixes all parameters
     /// to zero except the intercept (if it is used). A total of `test_ndf()`
-    /// parameters are constrained.
+    /// parameters are constrained. The likelihood includes the regularization
+    /// penalty of the null parameters, which is zero unless it is centered at a
+    /// prior mean.
     pub fn null_like(&self) -> F {
         let (null_like, _) = self.null_model_fit();
         null_like
@@ -305,6 +517,8 @@ where
                             },
                             // If we are in this branch it is because an intercept is needed.
                             use_intercept: true,
+                            standardization: None,
+                            aliased: Vec::new(),
                         };
                         // TODO: Make this function return an error, although it's
                         // difficult to imagine this case happening.
@@ -340,6 +554,9 @@ where
                     }
                 }
             };
+            // The penalty is not necessarily zero for the null parameters, for instance if it is
+            // centered at a prior mean.
+            let null_like = null_like + self.reg.likelihood(&null_intercept);
             *self.null_model.borrow_mut() = Some((null_like, null_intercept));
         }
         self.null_model
@@ -349,23 +566,151 @@ where
             .clone()
     }
 
-    /// Returns the expected value of Y given the input data X. This data need
-    /// not be the training data, so an option for linear offsets is provided.
-    /// Panics if the number of covariates in the data matrix is not consistent
-    /// with the training set. The data matrix may need to be padded by ones if
-    /// it is not part of a Model. The `utility::one_pad()` function facilitates
-    /// this.
-    pub fn predict<S>(&self, data_x: &ArrayBase<S, Ix2>, lin_off: Option<&Array1<F>>) -> Array1<F>
+    /// Returns the predictions given the covariate data X, either as the linear predictor or as
+    /// the expected value of Y. This data need not be the training data, so an option for linear
+    /// offsets is provided. The covariates should not include the intercept column; it is added
+    /// automatically if the model uses one, along with the standardization of the model if it
+    /// standardizes internally. Returns an error if the number of covariates or offsets is not
+    /// consistent with the data.
+    pub fn predict<S>(
+        &self,
+        data_x: &ArrayBase<S, Ix2>,
+        lin_off: Option<&Array1<F>>,
+        pred_type: PredictType,
+    ) -> RegressionResult<Array1<F>>
     where
         S: Data<Elem = F>,
     {
-        let lin_pred: Array1<F> = data_x.dot(&self.result);
-        let lin_pred: Array1<F> = if let Some(off) = &lin_off {
+        let design = self.design_matrix(data_x)?;
+        check_offset(&design, lin_off)?;
+        let lin_pred: Array1<F> = self.lin_pred_design(&design, lin_off);
+        Ok(match pred_type {
+            PredictType::Link => lin_pred,
+            PredictType::Response => lin_pred.mapv_into(M::Link::func_inv),
+        })
+    }
+
+    /// Returns the expected value of Y given a design matrix, which must already include the
+    /// intercept column and any standardization.
+    fn predict_design<S>(
+        &self,
+        design: &ArrayBase<S, Ix2>,
+        lin_off: Option<&Array1<F>>,
+    ) -> Array1<F>
+    where
+        S: Data<Elem = F>,
+    {
+        self.lin_pred_design(design, lin_off)
+            .mapv_into(M::Link::func_inv)
+    }
+
+    /// Returns the linear predictor given a design matrix.
+    fn lin_pred_design<S>(
+        &self,
+        design: &ArrayBase<S, Ix2>,
+        lin_off: Option<&Array1<F>>,
+    ) -> Array1<F>
+    where
+        S: Data<Elem = F>,
+    {
+        let lin_pred: Array1<F> = design.dot(&self.result);
+        if let Some(off) = &lin_off {
             lin_pred + *off
         } else {
             lin_pred
+        }
+    }
+
+    /// Returns the predictions for the input data X along with their standard errors and
+    /// confidence intervals at the given confidence level (e.g. 0.95). The interval is computed
+    /// as a normal interval on the linear predictor, using the standard error from
+    /// `covariance()`, with the endpoints then transformed through the inverse link function so
+    /// that it respects the domain of the response. The same conventions for the covariate data
+    /// and linear offsets as in `predict()` apply.
+    pub fn predict_with_se<S>(
+        &self,
+        data_x: &ArrayBase<S, Ix2>,
+        lin_off: Option<&Array1<F>>,
+        level: F,
+    ) -> RegressionResult<Prediction<F>>
+    where
+        S: Data<Elem = F>,
+    {
+        let z: F = F::from(norm_quantile(two_sided_quantile(level)?)).unwrap();
+        let design = self.design_matrix(data_x)?;
+        check_offset(&design, lin_off)?;
+        let lin_pred_se: Array1<F> = self.lin_pred_se(&design)?;
+        let lin_pred: Array1<F> = self.lin_pred_design(&design, lin_off);
+        let mean: Array1<F> = M::mean(&lin_pred);
+        let mean_se: Array1<F> =
+            M::d_mean(&lin_pred).mapv_into(num_traits::Float::abs) * &lin_pred_se;
+        let bound_a: Array1<F> = (&lin_pred - &(&lin_pred_se * z)).mapv_into(M::Link::func_inv);
+        let bound_b: Array1<F> = (&lin_pred + &(&lin_pred_se * z)).mapv_into(M::Link::func_inv);
+        // The inverse link function may be decreasing.
+        let lower = ndarray::Zip::from(&bound_a)
+            .and(&bound_b)
+            .map_collect(|&a, &b| if a < b { a } else { b });
+        let upper = ndarray::Zip::from(&bound_a)
+            .and(&bound_b)
+            .map_collect(|&a, &b| if a < b { b } else { a });
+        Ok(Prediction {
+            lin_pred,
+            lin_pred_se,
+            mean,
+            mean_se,
+            lower,
+            upper,
+        })
+    }
+
+    /// Returns the standard error of the linear predictor for each row of the design matrix,
+    /// `sqrt(x^T * Cov * x)`.
+    fn lin_pred_se(&self, design: &Array2<F>) -> RegressionResult<Array1<F>> {
+        let cov = self.covariance()?;
+        let var: Array1<F> = (&design.dot(&*cov) * design).sum_axis(Axis(1));
+        Ok(var.mapv_into(num_traits::Float::sqrt))
+    }
+
+    /// Returns several pseudo-R^2 measures of the fit relative to the null model, which has only
+    /// an intercept if one is used. The log-likelihoods are those given by `model_like` and
+    /// `null_like()`, which drop terms that depend only on the response. These terms vanish for
+    /// binary responses, but for other families the McFadden and Nagelkerke measures differ from
+    /// those computed with the fully normalized likelihood. For families with a free dispersion,
+    /// such as linear regression, the log-likelihoods are instead measured from that of the
+    /// saturated model in units of the estimated dispersion, `-D / (2 * phi)` for the deviance
+    /// `D`, so that the measures don't depend on the scale of the response. McFadden's measure is
+    /// then the fraction of the null deviance explained, which is R^2 for linear regression. The
+    /// likelihood of the fit includes its regularization.
+    pub fn pseudo_r_sq(&self) -> PseudoRSq<F> {
+        let (like, null_like) = match M::DISPERSED {
+            DispersionType::FreeDispersion => {
+                let sat_like: F = self.data.y.mapv(M::log_like_sat).sum();
+                let phi = self.dispersion();
+                (
+                    (self.model_like - sat_like) / phi,
+                    (self.null_like() - sat_like) / phi,
+                )
+            }
+            DispersionType::NoDispersion => (self.model_like, self.null_like()),
         };
-        lin_pred.mapv_into(M::Link::func_inv)
+        let n_data = F::from(self.n_data).unwrap();
+        let two = F::from(2.).unwrap();
+        let mcfadden = F::one() - like / null_like;
+        let mcfadden_adj = F::one() - (like - self.edf()) / null_like;
+        let cox_snell = F::one() - num_traits::Float::exp(two * (null_like - like) / n_data);
+        let cox_snell_max = F::one() - num_traits::Float::exp(two * null_like / n_data);
+        let nagelkerke = cox_snell / cox_snell_max;
+        let y_avg: F = self.data.y.mean().expect("Data should be non-empty");
+        let total_sum_sq: F = self.data.y.mapv(|y| (y - y_avg) * (y - y_avg)).sum();
+        let resid_sum_sq: F = self.resid_resp().mapv_into(|r| r * r).sum();
+        let efron = F::one() - resid_sum_sq / total_sum_sq;
+        PseudoRSq {
+            mcfadden,
+            mcfadden_adj,
+            cox_snell,
+            nagelkerke,
+            efron,
+        }
     }
 
     /// Return the deviance residuals for each point in the training data.
@@ -409,7 +754,7 @@ where
     /// These are not scaled by the sample standard deviation for families with a free dispersion
     /// parameter like linear regression.
     pub fn resid_pear(&self) -> Array1<F> {
-        let mu: Array1<F> = self.predict(&self.data.x, self.data.linear_offset.as_ref());
+        let mu: Array1<F> = self.predict_design(&self.data.x, self.data.linear_offset.as_ref());
         let residuals = &self.data.y - &mu;
         let var_diag: Array1<F> = mu.mapv_into(M::variance);
         let std: Array1<F> = var_diag.mapv_into(num_traits::Float::sqrt);
@@ -456,7 +801,7 @@ where
             // This is an approximation; the exact solution would perform a fit at each point.
             DispersionType::FreeDispersion => {
                 let dev = self.deviance();
-                let dof = F::from(self.ndf() - 1).unwrap();
+                let dof = self.ndf() - F::one();
                 let phi_i: Array1<F> = (-r_dev_sq / &omh + dev) / dof;
                 sum_quad / phi_i
             }
@@ -512,14 +857,34 @@ where
         Ok(score_alt.t().dot(&inv_fisher_alt.dot(&score_alt)))
     }
 
+    /// Draw `n_sims` new sets of responses from the fitted distribution of each observation in
+    /// the training data, using the estimated dispersion. Each row of the returned matrix is one
+    /// simulated response vector. An error is returned if the fitted distribution is undefined,
+    /// as for the dispersion of a saturated linear fit.
+    pub fn simulate<R>(&self, n_sims: usize, rng: &mut R) -> RegressionResult<Array2<F>>
+    where
+        R: Rng + ?Sized,
+    {
+        let mean: Array1<F> = M::mean(&self.data.linear_predictor(&self.result));
+        let phi = self.dispersion();
+        let mut sims = Array2::<F>::zeros((n_sims, self.n_data));
+        for mut row in sims.rows_mut() {
+            for (y, &mu) in row.iter_mut().zip(mean.iter()) {
+                *y = M::sample(mu, phi, rng)?;
+            }
+        }
+        Ok(sims)
+    }
+
     /// The degrees of freedom for the likelihood ratio test, the score test,
     /// and the Wald test. Not to be confused with `ndf()`, the degrees of
-    /// freedom in the model fit.
-    pub fn test_ndf(&self) -> usize {
+    /// freedom in the model fit. Under regularization this uses the effective
+    /// degrees of freedom `edf()`.
+    pub fn test_ndf(&self) -> F {
         if self.use_intercept {
-            self.n_par - 1
+            self.edf() - F::one()
         } else {
-            self.n_par
+            self.edf()
         }
     }
 
@@ -564,12 +929,69 @@ where
         (total_sum_sq - self.resid_sum_sq()) / total_sum_sq
     }
 
+    /// Returns the coefficient of multiple correlation adjusted for the number of parameters,
+    /// `1 - (1 - R^2) * (n - 1) / (n - p)`, where `p` is the effective number of parameters
+    /// `edf()` including the intercept, so that `n - p` is `ndf()`. Without regularization this is
+    /// the number of parameters.
+    pub fn r_sq_adj(&self) -> F {
+        let n_m1 = F::from(self.n_data - 1).unwrap();
+        F::one() - (F::one() - self.r_sq()) * n_m1 / self.ndf()
+    }
+
+    /// Returns the prediction intervals of new observations at the given level (e.g. 0.95) for the
+    /// covariate data X, as a tuple of the lower and upper bounds. Unlike the confidence interval of
+    /// the mean from `predict_with_se()`, these include the variance of the response about the
+    /// mean as estimated by the dispersion. The bounds use Student's t distribution with `ndf()`
+    /// degrees of freedom.
+    pub fn predict_interval<S>(
+        &self,
+        data_x: &ArrayBase<S, Ix2>,
+        lin_off: Option<&Array1<F>>,
+        level: F,
+    ) -> RegressionResult<(Array1<F>, Array1<F>)>
+    where
+        S: Data<Elem = F>,
+    {
+        let ndf = self.ndf().to_f64().unwrap();
+        let t: F = F::from(t_quantile(two_sided_quantile(level)?, ndf)).unwrap();
+        let design = self.design_matrix(data_x)?;
+        check_offset(&design, lin_off)?;
+        let lin_pred_se = self.lin_pred_se(&design)?;
+        let mean: Array1<F> = self.predict_design(&design, lin_off);
+        let phi: F = self.dispersion();
+        let pred_se: Array1<F> = lin_pred_se.mapv_into(|se| num_traits::Float::sqrt(se * se + phi));
+        let half_width: Array1<F> = pred_se * t;
+        Ok((&mean - &half_width, mean + half_width))
+    }
+
     /// Returns the residual sum of squares, i.e. the sum of the squared residuals.
     pub fn resid_sum_sq(&self) -> F {
         self.resid_resp().mapv_into(|r| r * r).sum()
     }
 }
 
+/// Check that the linear offsets, if provided, have an entry for each observation.
+fn check_offset<F: Float>(design: &Array2<F>, lin_off: Option<&Array1<F>>) -> RegressionResult<()> {
+    match lin_off {
+        Some(off) if off.len() != design.nrows() => Err(RegressionError::BadInput(
+            "Offsets must have same dimension as observations".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the upper quantile corresponding to a two-sided interval with the given confidence
+/// level, which must be between zero and one.
+fn two_sided_quantile<F: Float>(level: F) -> RegressionResult<f64> {
+    let level: f64 = level.to_f64().unwrap_or(f64::NAN);
+    if !(level > 0. && level < 1.) {
+        return Err(RegressionError::BadInput(format!(
+            "Confidence level must be between 0 and 1, found {level}"
+        )));
+    }
+    Ok(0.5 * (1. + level))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -580,7 +1002,7 @@ mod tests {
     };
     use anyhow::Result;
     use approx::assert_abs_diff_eq;
-    use ndarray::Axis;
+    use ndarray::{s, Axis};
 
     /// Checks if the test statistics are invariant based upon whether the data is standardized.
     #[test]
@@ -643,7 +1065,7 @@ mod tests {
             epsilon = 4.0 * f64::EPSILON
         );
         let empty_null_like = fit.null_like();
-        assert_eq!(fit.test_ndf(), 0);
+        assert_eq!(fit.test_ndf(), 0.);
         dbg!(&fit.model_like);
         let lr = fit.lr_test();
         // Since there is no data, the null likelihood should be identical to
@@ -702,7 +1124,7 @@ mod tests {
         let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
         let fit = model.fit()?;
         // The predicted values of Y given the model.
-        let pred_y = fit.predict(&one_pad(data_x.view()), None);
+        let pred_y = fit.predict(&data_x, None, PredictType::Response)?;
         let target_dev = (data_y - pred_y).mapv(|dy| dy * dy).sum();
         assert_abs_diff_eq!(fit.deviance(), target_dev,);
         Ok(())
@@ -726,7 +1148,7 @@ mod tests {
         let fit = model.fit()?;
         let dev = fit.deviance();
         let disp = fit.dispersion();
-        let ndf = fit.ndf() as f64;
+        let ndf = fit.ndf();
         assert_abs_diff_eq!(dev, disp * ndf, epsilon = 4. * f64::EPSILON);
         Ok(())
     }
@@ -796,7 +1218,7 @@ mod tests {
         // dbg!(target_null_like);
         let fit_null_like = fit.null_like();
         assert_abs_diff_eq!(2. * (fit.model_like - fit_null_like), fit.lr_test());
-        assert_eq!(fit.test_ndf(), 1);
+        assert_eq!(fit.test_ndf(), 1.);
         assert_abs_diff_eq!(
             fit_null_like,
             target_null_like,
@@ -833,6 +1255,122 @@ mod tests {
         Ok(())
     }
 
+    // Check the standard errors and intervals of the predictions.
+    #[test]
+    fn predict_with_se() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let x_new = array![[0.0], [1.5], [-4.0]];
+        let pred = fit.predict_with_se(&x_new, None, 0.95)?;
+        let cov = fit.covariance()?;
+        for (i, row) in one_pad(x_new.view()).outer_iter().enumerate() {
+            let var: f64 = row.dot(&cov.dot(&row));
+            assert_abs_diff_eq!(pred.lin_pred_se[i], var.sqrt(), epsilon = 1e-12);
+        }
+        // The identity link makes the linear predictor and the mean coincide.
+        assert_abs_diff_eq!(pred.mean, fit.predict(&x_new, None, PredictType::Response)?);
+        assert_abs_diff_eq!(pred.mean, pred.lin_pred);
+        assert_abs_diff_eq!(pred.mean_se, pred.lin_pred_se);
+        let z = 1.959963984540054;
+        assert_abs_diff_eq!(
+            pred.upper,
+            &pred.mean + &(&pred.lin_pred_se * z),
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(
+            pred.lower,
+            &pred.mean - &(&pred.lin_pred_se * z),
+            epsilon = 1e-12
+        );
+
+        // Prediction intervals include the dispersion and so must be wider.
+        let (lower, upper) = fit.predict_interval(&x_new, None, 0.95)?;
+        let t = crate::math::t_quantile(0.975, fit.ndf());
+        let half_width = pred
+            .lin_pred_se
+            .mapv(|se: f64| t * (se * se + fit.dispersion()).sqrt());
+        assert_abs_diff_eq!(upper, &pred.mean + &half_width, epsilon = 1e-12);
+        assert_abs_diff_eq!(lower, &pred.mean - &half_width, epsilon = 1e-12);
+
+        assert!(fit.predict_with_se(&x_new, None, 1.5).is_err());
+        assert!(fit
+            .predict_with_se(&one_pad(x_new.view()), None, 0.9)
+            .is_err());
+        Ok(())
+    }
+
+    // The confidence intervals of a logistic model should be asymmetric and stay in (0, 1).
+    #[test]
+    fn predict_with_se_logistic() -> Result<()> {
+        let data_y = array![true, true, false, true, true, false, false, false, true];
+        let data_x = array![0.4, 0.1, -0.3, 0.7, -0.5, -0.1, 0.8, 1.0, 0.4].insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let x_new = array![[-3.0], [0.2], [2.5]];
+        let pred = fit.predict_with_se(&x_new, None, 0.9)?;
+        let p = fit.predict(&x_new, None, PredictType::Response)?;
+        assert_abs_diff_eq!(pred.mean, p);
+        // d mu / d eta = p * (1 - p) for the logit link
+        let target_mean_se = &p * &p.mapv(|p| 1. - p) * &pred.lin_pred_se;
+        assert_abs_diff_eq!(pred.mean_se, target_mean_se, epsilon = 1e-12);
+        for i in 0..p.len() {
+            assert!(0. < pred.lower[i] && pred.lower[i] < p[i]);
+            assert!(p[i] < pred.upper[i] && pred.upper[i] < 1.);
+        }
+        Ok(())
+    }
+
+    // Check the prediction scales and the automatic transformation of new data.
+    #[test]
+    fn predict_new_data() -> Result<()> {
+        let data_y = array![true, true, false, true, true, false, false, false, true];
+        let data_x = array![
+            [0.4, 1.2],
+            [0.1, 0.3],
+            [-0.3, 0.5],
+            [0.7, -0.2],
+            [-0.5, 0.8],
+            [-0.1, 1.1],
+            [0.8, 0.2],
+            [1.0, -0.4],
+            [0.4, 0.0]
+        ];
+        let lin_off = array![0.1, 0.0, -0.1, 0.2, 0.1, 0.3, 0.4, -0.1, 0.1];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x)
+            .linear_offset(lin_off.clone())
+            .build()?;
+        let fit = model.fit()?;
+        let lin_pred = fit.predict(&data_x, Some(&lin_off), PredictType::Link)?;
+        assert_abs_diff_eq!(lin_pred, fit.data.linear_predictor(&fit.result));
+        let mean = fit.predict(&data_x, Some(&lin_off), PredictType::Response)?;
+        assert_abs_diff_eq!(mean, lin_pred.mapv(<Logistic as Glm>::Link::func_inv));
+        assert_abs_diff_eq!(&data_y.mapv(|y| y as u8 as f64) - &mean, fit.resid_resp());
+
+        // A model standardized internally should make the same predictions.
+        let model_std = ModelBuilder::<Logistic>::data(&data_y, &data_x)
+            .linear_offset(lin_off.clone())
+            .standardize()
+            .build()?;
+        let fit_std = model_std.fit()?;
+        let x_new = array![[0.3, 0.2], [-1.2, 2.0]];
+        assert_abs_diff_eq!(
+            fit.predict(&x_new, None, PredictType::Link)?,
+            fit_std.predict(&x_new, None, PredictType::Link)?,
+            epsilon = 1e-10
+        );
+
+        // Inconsistent dimensions are errors rather than panics.
+        assert!(fit
+            .predict(&one_pad(x_new.view()), None, PredictType::Response)
+            .is_err());
+        assert!(fit
+            .predict(&x_new, Some(&lin_off), PredictType::Response)
+            .is_err());
+        Ok(())
+    }
+
     // Check the consistency of the various statistical tests for linear
     // regression, where they should be the most comparable.
     #[test]
@@ -848,4 +1386,495 @@ mod tests {
         assert_abs_diff_eq!(lr, score, epsilon = 32.0 * f64::EPSILON);
         Ok(())
     }
+
+    #[test]
+    fn simulate() -> Result<()> {
+        use crate::Poisson;
+        use rand::{rngs::StdRng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(0);
+        let n_sims = 4000;
+
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let sims = fit.simulate(n_sims, &mut rng)?;
+        assert_eq!(sims.dim(), (n_sims, data_y.len()));
+        let fitted = fit.predict(&data_x, None, PredictType::Response)?;
+        let sim_mean = sims.mean_axis(Axis(0)).unwrap();
+        let sim_var = sims.var_axis(Axis(0), 1.);
+        let phi = fit.dispersion();
+        assert_abs_diff_eq!(
+            sim_mean,
+            fitted,
+            epsilon = 5. * (phi / n_sims as f64).sqrt()
+        );
+        for var in sim_var {
+            assert_abs_diff_eq!(var, phi, epsilon = 0.1 * phi);
+        }
+
+        let data_y = array![true, false, false, true, true, false, true, true];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let sims = fit.simulate(n_sims, &mut rng)?;
+        assert!(sims.iter().all(|&y| y == 0. || y == 1.));
+        let fitted = fit.predict(&data_x, None, PredictType::Response)?;
+        assert_abs_diff_eq!(sims.mean_axis(Axis(0)).unwrap(), fitted, epsilon = 0.05);
+
+        let data_y = array![1u32, 4, 0, 3, 2, 1, 0, 2];
+        let model = ModelBuilder::<Poisson>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let sims = fit.simulate(n_sims, &mut rng)?;
+        assert!(sims.iter().all(|&y| y >= 0. && y.fract() == 0.));
+        let fitted = fit.predict(&data_x, None, PredictType::Response)?;
+        let sim_mean = sims.mean_axis(Axis(0)).unwrap();
+        for (m, mu) in sim_mean.iter().zip(fitted.iter()) {
+            assert_abs_diff_eq!(*m, *mu, epsilon = 5. * (mu / n_sims as f64).sqrt());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pseudo_r_sq() -> Result<()> {
+        let data_y = array![true, false, false, true, true, false, true, true, false, true];
+        let data_x =
+            array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2, -0.8].insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let r_sq = fit.pseudo_r_sq();
+        let (like, null_like) = (fit.model_like, fit.null_like());
+        assert_abs_diff_eq!(r_sq.mcfadden, 1. - like / null_like);
+        assert_abs_diff_eq!(r_sq.mcfadden_adj, 1. - (like - 2.) / null_like);
+        // For binary responses, McFadden's measure is the fraction of the null deviance explained.
+        let null_dev = -2. * null_like;
+        assert_abs_diff_eq!(
+            r_sq.mcfadden,
+            1. - fit.deviance() / null_dev,
+            epsilon = 1e-12
+        );
+        // The Cox-Snell measure follows from the likelihood-ratio statistic.
+        assert_abs_diff_eq!(
+            r_sq.cox_snell,
+            1. - f64::exp(-fit.lr_test() / 10.),
+            epsilon = 1e-12
+        );
+        for val in [r_sq.mcfadden, r_sq.cox_snell, r_sq.nagelkerke, r_sq.efron] {
+            assert!((0. ..1.).contains(&val));
+        }
+        assert!(r_sq.mcfadden_adj < r_sq.mcfadden);
+        assert!(r_sq.nagelkerke > r_sq.cox_snell);
+
+        // The adjustment uses the effective number of parameters under regularization.
+        let fit_ridge = model.fit_options().l2_reg(1.).fit()?;
+        let edf = fit_ridge.edf();
+        assert!(edf > 1. && edf < 2.);
+        assert_abs_diff_eq!(
+            fit_ridge.pseudo_r_sq().mcfadden_adj,
+            1. - (fit_ridge.model_like - edf) / fit_ridge.null_like(),
+            epsilon = 1e-12
+        );
+        Ok(())
+    }
+
+    /// For linear regression the measures don't depend on the scale of the response.
+    #[test]
+    fn pseudo_r_sq_linear() -> Result<()> {
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let noise = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_y: Array1<f64> = &data_x.column(0) * 0.5 + &noise;
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let r_sq = fit.pseudo_r_sq();
+        assert_abs_diff_eq!(r_sq.mcfadden, fit.r_sq(), epsilon = 1e-12);
+        for val in [
+            r_sq.mcfadden,
+            r_sq.mcfadden_adj,
+            r_sq.cox_snell,
+            r_sq.nagelkerke,
+            r_sq.efron,
+        ] {
+            assert!((0. ..=1.).contains(&val), "{val} is not in [0, 1]");
+        }
+        for scale in [10., 100.] {
+            let data_y_scaled: Array1<f64> = &data_y * scale;
+            let model = ModelBuilder::<Linear>::data(&data_y_scaled, &data_x).build()?;
+            let r_sq_scaled = model.fit()?.pseudo_r_sq();
+            assert_abs_diff_eq!(r_sq_scaled.mcfadden, r_sq.mcfadden, epsilon = 1e-10);
+            assert_abs_diff_eq!(r_sq_scaled.mcfadden_adj, r_sq.mcfadden_adj, epsilon = 1e-10);
+            assert_abs_diff_eq!(r_sq_scaled.cox_snell, r_sq.cox_snell, epsilon = 1e-10);
+            assert_abs_diff_eq!(r_sq_scaled.nagelkerke, r_sq.nagelkerke, epsilon = 1e-10);
+            assert_abs_diff_eq!(r_sq_scaled.efron, r_sq.efron, epsilon = 1e-10);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn r_sq_linear() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let r_sq = fit.r_sq();
+        assert_abs_diff_eq!(fit.pseudo_r_sq().efron, r_sq, epsilon = 1e-12);
+        assert_abs_diff_eq!(fit.r_sq_adj(), 1. - (1. - r_sq) * 7. / 6., epsilon = 1e-12);
+
+        // Under a ridge penalty the adjustment uses the effective number of parameters.
+        let fit_ridge = model.fit_options().l2_reg(2.).fit()?;
+        let edf = fit_ridge.edf();
+        assert!(edf > 1. && edf < 2.);
+        assert_abs_diff_eq!(
+            fit_ridge.r_sq_adj(),
+            1. - (1. - fit_ridge.r_sq()) * 7. / (8. - edf),
+            epsilon = 1e-12
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exp_coef() -> Result<()> {
+        use crate::{link::ExpCoefKind, Poisson};
+        let data_x: Array2<f64> =
+            array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let data_y = array![true, false, false, true, true, false, true, true];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let odds = fit.exp_coef(0.95)?;
+        assert_eq!(odds.kind, ExpCoefKind::OddsRatio);
+        assert_eq!(odds.kind.to_string(), "odds ratio");
+        let cov = fit.covariance()?;
+        for i in 0..2 {
+            let se: f64 = cov[[i, i]].sqrt();
+            assert_abs_diff_eq!(odds.values[i], fit.result[i].exp());
+            assert_abs_diff_eq!(
+                odds.lower[i],
+                (fit.result[i] - 1.959963984540054 * se).exp(),
+                epsilon = 1e-12
+            );
+            assert_abs_diff_eq!(
+                odds.lower[i] * odds.upper[i],
+                odds.values[i].powi(2),
+                epsilon = 1e-12
+            );
+        }
+
+        let data_y = array![1u32, 4, 0, 3, 2, 1, 0, 2];
+        let model = ModelBuilder::<Poisson>::data(&data_y, &data_x).build()?;
+        assert_eq!(model.fit()?.exp_coef(0.9)?.kind, ExpCoefKind::RateRatio);
+
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        assert_eq!(model.fit()?.exp_coef(0.9)?.kind, ExpCoefKind::ExpCoef);
+        Ok(())
+    }
+
+    /// Redundant dummy columns are dropped and reported as NaN, and predictions use the full
+    /// covariate data.
+    #[test]
+    fn drop_aliased() -> Result<()> {
+        use crate::MarginType;
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        // The second dummy column duplicates the first, and the last is the sum of the first and
+        // the third.
+        let data_x = array![
+            [1., 1., 0.3, 1.3],
+            [0., 0., 0.6, 0.6],
+            [1., 1., -0.2, 0.8],
+            [0., 0., 0.1, 0.1],
+            [1., 1., 0.9, 1.9],
+            [0., 0., -0.4, -0.4],
+            [1., 1., 0.5, 1.5],
+            [0., 0., 0.7, 0.7]
+        ];
+        assert!(ModelBuilder::<Linear>::data(&data_y, &data_x)
+            .colinear_tol(1e-10)
+            .build()
+            .is_err());
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x)
+            .drop_aliased()
+            .build()?;
+        let fit = model.fit()?;
+        assert_eq!(fit.aliased(), &[2, 4]);
+        let coef: Array1<f64> = fit.coefficients();
+        assert_eq!(coef.len(), 5);
+        assert!(coef[2].is_nan() && coef[4].is_nan());
+
+        let data_x_ident = data_x.select(Axis(1), &[0, 2]);
+        let model_ident = ModelBuilder::<Linear>::data(&data_y, &data_x_ident).build()?;
+        let fit_ident = model_ident.fit()?;
+        assert_abs_diff_eq!(fit.result, fit_ident.result, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            fit.predict(&data_x, None, PredictType::Response)?,
+            fit_ident.predict(&data_x_ident, None, PredictType::Response)?,
+            epsilon = 1e-12
+        );
+        assert!(fit
+            .predict(&data_x_ident, None, PredictType::Response)
+            .is_err());
+
+        // Marginal effects are reported for the remaining covariates.
+        let model_std = ModelBuilder::<Linear>::data(&data_y, &data_x)
+            .standardize()
+            .drop_aliased()
+            .build()?;
+        let fit_std = model_std.fit()?;
+        assert_eq!(fit_std.aliased(), &[2, 4]);
+        assert_abs_diff_eq!(
+            fit_std.marginal_effects(MarginType::Average)?.effects,
+            fit.result.slice(ndarray::s![1..]),
+            epsilon = 1e-10
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn effective_dof() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![
+            [0.6, 0.2],
+            [2.1, 0.4],
+            [0.4, -0.3],
+            [-3.2, 0.1],
+            [0.7, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.5],
+            [0.5, 0.0]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        assert_abs_diff_eq!(fit.edf(), 3., epsilon = 1e-12);
+        assert_abs_diff_eq!(fit.ndf(), 5., epsilon = 1e-12);
+
+        // The effective degrees of freedom of ridge regression are the trace of its hat matrix.
+        let l2 = 2.;
+        let fit_ridge = model.fit_options().l2_reg(l2).fit()?;
+        let x = one_pad(data_x.view());
+        let penalty: Array2<f64> = Array2::from_diag(&array![0., l2, l2]);
+        let hat: Array2<f64> = x.dot(&(x.t().dot(&x) + penalty).inv_into()?).dot(&x.t());
+        let edf: f64 = hat.diag().sum();
+        assert_abs_diff_eq!(fit_ridge.edf(), edf, epsilon = 1e-10);
+        assert!(edf < 3. && edf > 1.);
+        assert_abs_diff_eq!(fit_ridge.ndf(), 8. - edf, epsilon = 1e-10);
+        assert_abs_diff_eq!(fit_ridge.test_ndf(), edf - 1., epsilon = 1e-10);
+        assert_abs_diff_eq!(
+            fit_ridge.dispersion(),
+            fit_ridge.deviance() / (8. - edf),
+            epsilon = 1e-10
+        );
+        // The information criteria use the unpenalized likelihood.
+        let data_like =
+            fit_ridge.model_like + 0.5 * l2 * fit_ridge.result.slice(s![1..]).mapv(|b| b * b).sum();
+        assert_abs_diff_eq!(fit_ridge.aic(), 2. * edf - 2. * data_like, epsilon = 1e-10);
+        assert_abs_diff_eq!(
+            fit_ridge.bic(),
+            f64::ln(8.) * edf - 2. * data_like,
+            epsilon = 1e-10
+        );
+
+        // A strong L1 penalty leaves only the intercept active.
+        let fit_lasso = model.fit_options().max_iter(256).l1_reg(10.).fit()?;
+        assert_abs_diff_eq!(fit_lasso.edf(), 1.);
+        // The elastic net is bounded by the lasso and the ridge penalties alone.
+        let fit_enet = model
+            .fit_options()
+            .max_iter(256)
+            .l1_reg(0.01)
+            .l2_reg(l2)
+            .fit()?;
+        assert!(fit_enet.edf() <= edf + 1e-8);
+        Ok(())
+    }
+
+    /// A non-negative logistic regression satisfies the optimality conditions of the bounds.
+    #[test]
+    fn non_negative_logistic() -> Result<()> {
+        let data_y = array![
+            true, false, true, false, true, false, false, true, true, false, true, false, true,
+            false
+        ];
+        let data_x = array![
+            [0.5, -0.6],
+            [0.1, 0.1],
+            [-0.3, -0.2],
+            [0.4, 0.5],
+            [0.6, -0.1],
+            [0.1, 0.4],
+            [-0.2, 0.4],
+            [0.9, -0.3],
+            [0.2, 0.2],
+            [-0.1, 0.7],
+            [-0.4, 0.1],
+            [0.7, -0.2],
+            [0.3, -0.3],
+            [-0.5, 0.5]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit_free = model.fit()?;
+        assert!(fit_free.result[2] < 0.);
+        let fit = model.fit_options().non_negative().fit()?;
+        assert_eq!(fit.result[2], 0.);
+        assert!(fit.result[1] > 0.);
+        assert_eq!(fit.active_bounds(), vec![2]);
+        // The score vanishes for the free parameters, and points out of the feasible region for
+        // the parameter at its bound.
+        let score = fit.score(&fit.result);
+        assert_abs_diff_eq!(score[0], 0., epsilon = 1e-8);
+        assert_abs_diff_eq!(score[1], 0., epsilon = 1e-8);
+        assert!(score[2] < 0.);
+        assert_abs_diff_eq!(fit.edf(), 2., epsilon = 1e-12);
+        assert!(fit.lr_test_against(&fit_free.result) < 0.);
+        Ok(())
+    }
+
+    /// A sum-to-zero constraint on a full set of dummy columns is equivalent to the effect
+    /// coding of the categorical variable.
+    #[test]
+    fn sum_to_zero_constraint() -> Result<()> {
+        use crate::Linear;
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2, 0.9];
+        let level = [0, 1, 2, 0, 1, 2, 0, 1, 2];
+        let cont = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2];
+        let data_x: Array2<f64> = Array2::from_shape_fn((9, 4), |(i, j)| match j {
+            0 => cont[i],
+            _ => f64::from(u8::from(level[i] == j - 1)),
+        });
+        // The intercept and dummies are collinear, so only the constraint identifies the model.
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x)
+            .colinear_tol(-1.)
+            .build()?;
+        let c_mat = array![[0., 0., 1., 1., 1.]];
+        let fit = model
+            .fit_options()
+            .constraints(c_mat.clone(), array![0.])
+            .fit()?;
+        assert_abs_diff_eq!(c_mat.dot(&fit.result)[0], 0., epsilon = 1e-12);
+
+        // The effect coding of the last level by -1 for each of the others
+        let transform = array![
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+            [0., 0., -1., -1.]
+        ];
+        let effect_x: Array2<f64> = Array2::from_shape_fn((9, 3), |(i, j)| match j {
+            0 => cont[i],
+            _ => data_x[[i, j]] - data_x[[i, 3]],
+        });
+        let effect_model = ModelBuilder::<Linear>::data(&data_y, &effect_x).build()?;
+        let effect_fit = effect_model.fit()?;
+        assert_abs_diff_eq!(
+            fit.result,
+            transform.dot(&effect_fit.result),
+            epsilon = 1e-10
+        );
+        assert_abs_diff_eq!(fit.edf(), 4., epsilon = 1e-12);
+        assert_abs_diff_eq!(fit.dispersion(), effect_fit.dispersion(), epsilon = 1e-10);
+        let effect_cov = transform
+            .dot(&*effect_fit.covariance()?)
+            .dot(&transform.t());
+        assert_abs_diff_eq!(*fit.covariance()?, effect_cov, epsilon = 1e-10);
+
+        assert!(model
+            .fit_options()
+            .constraints(array![[0., 0., 1., 1.]], array![0.])
+            .fit()
+            .is_err());
+        assert!(model
+            .fit_options()
+            .constraints(c_mat, array![0., 1.])
+            .fit()
+            .is_err());
+        Ok(())
+    }
+
+    /// The sum-to-zero constraint is also equivalent to the effect coding for a Poisson model,
+    /// which is not solved in a single IRLS step.
+    #[test]
+    fn sum_to_zero_constraint_poisson() -> Result<()> {
+        use crate::Poisson;
+        let data_y = array![1u32, 4, 0, 3, 2, 1, 0, 2, 5];
+        let level = [0, 1, 2, 0, 1, 2, 0, 1, 2];
+        let cont = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2];
+        let data_x: Array2<f64> = Array2::from_shape_fn((9, 4), |(i, j)| match j {
+            0 => cont[i],
+            _ => f64::from(u8::from(level[i] == j - 1)),
+        });
+        let model = ModelBuilder::<Poisson>::data(&data_y, &data_x)
+            .colinear_tol(-1.)
+            .build()?;
+        let c_mat = array![[0., 0., 1., 1., 1.]];
+        let fit = model
+            .fit_options()
+            .constraints(c_mat.clone(), array![0.])
+            .fit()?;
+        assert_abs_diff_eq!(c_mat.dot(&fit.result)[0], 0., epsilon = 1e-12);
+
+        let transform = array![
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+            [0., 0., -1., -1.]
+        ];
+        let effect_x: Array2<f64> = Array2::from_shape_fn((9, 3), |(i, j)| match j {
+            0 => cont[i],
+            _ => data_x[[i, j]] - data_x[[i, 3]],
+        });
+        let effect_model = ModelBuilder::<Poisson>::data(&data_y, &effect_x).build()?;
+        let effect_fit = effect_model.fit()?;
+        assert_abs_diff_eq!(
+            fit.result,
+            transform.dot(&effect_fit.result),
+            epsilon = 1e-8
+        );
+        assert_abs_diff_eq!(fit.deviance(), effect_fit.deviance(), epsilon = 1e-10);
+        let effect_cov = transform
+            .dot(&*effect_fit.covariance()?)
+            .dot(&transform.t());
+        assert_abs_diff_eq!(*fit.covariance()?, effect_cov, epsilon = 1e-8);
+        Ok(())
+    }
+
+    /// Constraints with an inhomogeneous right-hand side hold exactly at the result under ridge
+    /// and lasso penalties. The ridge result is stationary in the null space of the constraints.
+    #[test]
+    fn constraints_with_penalty() -> Result<()> {
+        let data_y = array![true, false, false, true, true, false, true, true, false, true];
+        let data_x = array![
+            [0.6, 0.2],
+            [2.1, -0.3],
+            [0.4, 1.1],
+            [-3.2, 0.4],
+            [0.7, -0.8],
+            [0.1, 0.5],
+            [-0.3, -1.2],
+            [0.5, 0.9],
+            [1.2, 0.1],
+            [-0.8, -0.6]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let c_mat = array![[0., 1., -1.]];
+        let d_vec = array![0.5];
+
+        let fit_ridge = model
+            .fit_options()
+            .l2_reg(0.5)
+            .constraints(c_mat.clone(), d_vec.clone())
+            .fit()?;
+        assert_abs_diff_eq!(c_mat.dot(&fit_ridge.result), d_vec, epsilon = 1e-12);
+        // The penalized score must be a multiple of the constraint row.
+        let score = fit_ridge.score(&fit_ridge.result);
+        assert_abs_diff_eq!(score[0], 0., epsilon = 1e-6);
+        assert_abs_diff_eq!(score[1] + score[2], 0., epsilon = 1e-6);
+        let fit_free = model.fit_options().l2_reg(0.5).fit()?;
+        assert!(c_mat.dot(&fit_free.result)[0] < 0.4);
+
+        let fit_lasso = model
+            .fit_options()
+            .l1_reg(0.5)
+            .constraints(c_mat.clone(), d_vec.clone())
+            .fit()?;
+        assert_abs_diff_eq!(c_mat.dot(&fit_lasso.result), d_vec, epsilon = 1e-12);
+        Ok(())
+    }
 }
@@ -0,0 +1,309 @@
+//! Bootstrap estimates of the sampling distribution of the parameters by re-fitting the model to
+//! resampled data. These can be more reliable than the asymptotic covariance for small samples.
+use super::{jackknife::Jackknife, two_sided_quantile, Fit};
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::Glm,
+    math::{norm_cdf, norm_quantile},
+    num::Float,
+    Linear,
+};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::Rng;
+
+/// The parameters re-fit to each bootstrap replicate of the data.
+#[derive(Clone, Debug)]
+pub struct Bootstrap<F> {
+    /// The parameters of the original fit.
+    pub estimate: Array1<F>,
+    /// The parameters of each re-fit, with one row per replicate and one column per parameter.
+    pub results: Array2<F>,
+}
+
+impl<F> Bootstrap<F>
+where
+    F: Float,
+{
+    /// The bootstrap estimate of the covariance matrix of the parameters.
+    pub fn covariance(&self) -> Array2<F> {
+        let n_boot = F::from(self.results.nrows()).unwrap();
+        let mean: Array1<F> = self.results.mean_axis(Axis(0)).unwrap();
+        let centered: Array2<F> = &self.results - &mean.insert_axis(Axis(0));
+        centered.t().dot(&centered) / (n_boot - F::one())
+    }
+
+    /// Returns the lower and upper bounds of the two-sided percentile interval of each parameter
+    /// at the given confidence level.
+    pub fn percentile_interval(&self, level: F) -> RegressionResult<(Array1<F>, Array1<F>)> {
+        let p_upper = two_sided_quantile(level)?;
+        let mut lower = Array1::<F>::zeros(self.estimate.len());
+        let mut upper = Array1::<F>::zeros(self.estimate.len());
+        for (i, col) in self.results.columns().into_iter().enumerate() {
+            let sorted = sorted_f64(col);
+            lower[i] = F::from(quantile_sorted(&sorted, 1. - p_upper)).unwrap();
+            upper[i] = F::from(quantile_sorted(&sorted, p_upper)).unwrap();
+        }
+        Ok((lower, upper))
+    }
+
+    /// Returns the lower and upper bounds of the two-sided bias-corrected and accelerated (BCa)
+    /// interval of each parameter at the given confidence level. The acceleration is estimated
+    /// from the leave-one-out jackknife of the same fit, as given by `Fit::jackknife()`.
+    pub fn bca_interval(
+        &self,
+        level: F,
+        jackknife: &Jackknife<F>,
+    ) -> RegressionResult<(Array1<F>, Array1<F>)> {
+        if jackknife.fold_results.ncols() != self.estimate.len() {
+            return Err(RegressionError::BadInput(
+                "The jackknife must have the same number of parameters as the bootstrap"
+                    .to_string(),
+            ));
+        }
+        let p_upper = two_sided_quantile(level)?;
+        let z_upper = norm_quantile(p_upper);
+        let n_boot = self.results.nrows() as f64;
+        let mut lower = Array1::<F>::zeros(self.estimate.len());
+        let mut upper = Array1::<F>::zeros(self.estimate.len());
+        for (i, col) in self.results.columns().into_iter().enumerate() {
+            let sorted = sorted_f64(col);
+            let est = self.estimate[i].to_f64().unwrap();
+            // The bias correction is clamped so that it remains finite when every replicate is on
+            // one side of the estimate.
+            let frac_below = sorted.iter().filter(|&&b| b < est).count() as f64 / n_boot;
+            let frac_below = frac_below.clamp(0.5 / n_boot, 1. - 0.5 / n_boot);
+            let z_0 = norm_quantile(frac_below);
+            let accel = acceleration(jackknife.fold_results.column(i));
+            let adjusted = |z: f64| {
+                let z_sum = z_0 + z;
+                norm_cdf(z_0 + z_sum / (1. - accel * z_sum))
+            };
+            lower[i] = F::from(quantile_sorted(&sorted, adjusted(-z_upper))).unwrap();
+            upper[i] = F::from(quantile_sorted(&sorted, adjusted(z_upper))).unwrap();
+        }
+        Ok((lower, upper))
+    }
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Perform a nonparametric bootstrap by re-fitting the model to `n_boot` samples of the
+    /// observations drawn with replacement. Each re-fit uses the same options as this fit and is
+    /// warm-started from its result. An error is returned if any re-fit fails, which is possible
+    /// if a resampled dataset is degenerate.
+    pub fn bootstrap_cases<R>(&self, n_boot: usize, rng: &mut R) -> RegressionResult<Bootstrap<F>>
+    where
+        R: Rng + ?Sized,
+    {
+        self.bootstrap_with(n_boot, |fit| {
+            let rows: Vec<usize> = (0..fit.n_data)
+                .map(|_| rng.gen_range(0..fit.n_data))
+                .collect();
+            fit.refit_rows(&rows, &fit.data.y)
+        })
+    }
+
+    /// Perform a parametric bootstrap by re-fitting the model to `n_boot` sets of responses drawn
+    /// from the fitted distribution of each observation, using the estimated dispersion. An error
+    /// is returned if the fitted distribution is undefined, as in `simulate()`.
+    pub fn bootstrap_parametric<R>(
+        &self,
+        n_boot: usize,
+        rng: &mut R,
+    ) -> RegressionResult<Bootstrap<F>>
+    where
+        R: Rng + ?Sized,
+    {
+        let sims: Array2<F> = self.simulate(n_boot, rng)?;
+        let mut sims_iter = sims.rows().into_iter();
+        let rows: Vec<usize> = (0..self.n_data).collect();
+        self.bootstrap_with(n_boot, |fit| {
+            let y: Array1<F> = sims_iter.next().unwrap().to_owned();
+            fit.refit_rows(&rows, &y)
+        })
+    }
+
+    fn bootstrap_with<G>(&self, n_boot: usize, mut refit: G) -> RegressionResult<Bootstrap<F>>
+    where
+        G: FnMut(&Self) -> RegressionResult<Array1<F>>,
+    {
+        if n_boot < 2 {
+            return Err(RegressionError::BadInput(
+                "The bootstrap requires at least two replicates".to_string(),
+            ));
+        }
+        let mut results = Array2::<F>::zeros((n_boot, self.n_par));
+        for mut row in results.rows_mut() {
+            row.assign(&refit(self)?);
+        }
+        Ok(Bootstrap {
+            estimate: self.result.clone(),
+            results,
+        })
+    }
+}
+
+impl<'a, F> Fit<'a, Linear, F>
+where
+    F: 'static + Float,
+{
+    /// Perform a residual bootstrap by re-fitting the model to `n_boot` sets of responses formed
+    /// by adding resampled residuals to the fitted values. The residuals are corrected for their
+    /// leverage and centered before resampling. An error is returned for a saturated fit, which
+    /// has no residual degrees of freedom.
+    pub fn bootstrap_residuals<R>(
+        &self,
+        n_boot: usize,
+        rng: &mut R,
+    ) -> RegressionResult<Bootstrap<F>>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.ndf() <= F::zero() {
+            return Err(RegressionError::BadInput(
+                "The residual bootstrap requires residual degrees of freedom".to_string(),
+            ));
+        }
+        let fitted: Array1<F> = self.data.linear_predictor(&self.result);
+        let omh: Array1<F> = -self.data.leverage()? + F::one();
+        let resid: Array1<F> = self.resid_resp() / omh.mapv_into(num_traits::Float::sqrt);
+        let resid: Array1<F> = &resid - resid.mean().unwrap();
+        let rows: Vec<usize> = (0..self.n_data).collect();
+        self.bootstrap_with(n_boot, |fit| {
+            let y: Array1<F> = fitted.mapv(|mu| mu + resid[rng.gen_range(0..fit.n_data)]);
+            fit.refit_rows(&rows, &y)
+        })
+    }
+}
+
+/// The acceleration of the BCa interval, estimated from the skewness of the jackknife results.
+fn acceleration<F: Float>(jack: ArrayView1<F>) -> f64 {
+    let jack: Array1<f64> = jack.mapv(|b| b.to_f64().unwrap());
+    let diff: Array1<f64> = jack.mean().unwrap() - jack;
+    let sum_sq = diff.mapv(|d| d * d).sum();
+    if sum_sq == 0. {
+        return 0.;
+    }
+    diff.mapv(|d| d * d * d).sum() / (6. * sum_sq.powf(1.5))
+}
+
+fn sorted_f64<F: Float>(values: ArrayView1<F>) -> Vec<f64> {
+    let mut sorted: Vec<f64> = values.iter().map(|b| b.to_f64().unwrap()).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted
+}
+
+/// The sample quantile of sorted values, linearly interpolating between order statistics.
+fn quantile_sorted(sorted: &[f64], p: f64) -> f64 {
+    let pos = p.clamp(0., 1.) * (sorted.len() - 1) as f64;
+    let below = pos.floor() as usize;
+    let above = pos.ceil() as usize;
+    let frac = pos - below as f64;
+    sorted[below] + frac * (sorted[above] - sorted[below])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quantile_sorted, Bootstrap};
+    use crate::{fit::jackknife::Jackknife, model::ModelBuilder, Linear, Poisson};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, s, Array1, Array2};
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+
+    #[test]
+    fn quantiles() {
+        let sorted = [1., 2., 4., 8.];
+        assert_abs_diff_eq!(quantile_sorted(&sorted, 0.), 1.);
+        assert_abs_diff_eq!(quantile_sorted(&sorted, 0.5), 3.);
+        assert_abs_diff_eq!(quantile_sorted(&sorted, 1.), 8.);
+    }
+
+    /// With a symmetric bootstrap distribution centered on the estimate and no skew in the
+    /// jackknife, the BCa interval reduces to the percentile interval.
+    #[test]
+    fn bca_symmetric() -> Result<()> {
+        let results: Array2<f64> = Array1::linspace(-1., 1., 100).insert_axis(ndarray::Axis(1));
+        let boot = Bootstrap {
+            estimate: array![0.],
+            results,
+        };
+        let jack = Jackknife {
+            fold_results: array![[-0.1], [0.0], [0.1]],
+            bias: array![0.],
+            covariance: array![[0.01]],
+        };
+        let (lo_pct, hi_pct) = boot.percentile_interval(0.9)?;
+        let (lo_bca, hi_bca) = boot.bca_interval(0.9, &jack)?;
+        assert_abs_diff_eq!(lo_pct[0], -hi_pct[0], epsilon = 1e-12);
+        assert_abs_diff_eq!(hi_pct[0], 0.9, epsilon = 0.01);
+        // Half of the replicates are below the estimate, so the bias correction is zero.
+        assert_abs_diff_eq!(lo_bca, lo_pct, epsilon = 1e-12);
+        assert_abs_diff_eq!(hi_bca, hi_pct, epsilon = 1e-12);
+        assert!(boot.percentile_interval(1.5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bootstrap_linear() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let noise = Normal::new(0., 0.5)?;
+        let data_x: Array2<f64> = Array1::linspace(0., 2., 40).insert_axis(ndarray::Axis(1));
+        let data_y: Array1<f64> = data_x
+            .column(0)
+            .mapv(|x| 0.5 + 1.5 * x + noise.sample(&mut rng));
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let cov = fit.covariance()?;
+        let n_boot = 400;
+        let resid_boot = fit.bootstrap_residuals(n_boot, &mut rng)?;
+        let param_boot = fit.bootstrap_parametric(n_boot, &mut rng)?;
+        let case_boot = fit.bootstrap_cases(n_boot, &mut rng)?;
+        for boot in [&resid_boot, &param_boot, &case_boot] {
+            assert_eq!(boot.results.dim(), (n_boot, 2));
+            let boot_cov = boot.covariance();
+            for i in 0..2 {
+                // The bootstrap variance should roughly agree with the asymptotic variance.
+                let ratio = boot_cov[[i, i]] / cov[[i, i]];
+                assert!(ratio > 0.6 && ratio < 1.5, "variance ratio {ratio}");
+            }
+            let (lower, upper) = boot.percentile_interval(0.9)?;
+            for i in 0..2 {
+                assert!(lower[i] < fit.result[i] && fit.result[i] < upper[i]);
+            }
+        }
+        // The bootstrap is reproducible with a seeded generator.
+        let boot_a = fit.bootstrap_cases(10, &mut StdRng::seed_from_u64(1))?;
+        let boot_b = fit.bootstrap_cases(10, &mut StdRng::seed_from_u64(1))?;
+        assert_eq!(boot_a.results, boot_b.results);
+
+        // A saturated fit has no residual variance to resample or simulate from.
+        let data_x_sat: Array2<f64> = array![[0.], [1.]];
+        let model_sat =
+            ModelBuilder::<Linear>::data(&data_y.slice(s![..2]), &data_x_sat).build()?;
+        let fit_sat = model_sat.fit()?;
+        assert!(fit_sat.simulate(1, &mut rng).is_err());
+        assert!(fit_sat.bootstrap_parametric(n_boot, &mut rng).is_err());
+        assert!(fit_sat.bootstrap_residuals(n_boot, &mut rng).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bootstrap_poisson_bca() -> Result<()> {
+        let data_y = array![1u32, 0, 2, 3, 1, 4, 2, 6, 5, 8, 7, 12];
+        let data_x: Array2<f64> = Array1::linspace(0., 1.1, 12).insert_axis(ndarray::Axis(1));
+        let model = ModelBuilder::<Poisson>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let boot = fit.bootstrap_parametric(200, &mut StdRng::seed_from_u64(2))?;
+        let jack = fit.jackknife()?;
+        let (lower, upper) = boot.bca_interval(0.95, &jack)?;
+        for i in 0..2 {
+            assert!(lower[i] < fit.result[i] && fit.result[i] < upper[i]);
+        }
+        Ok(())
+    }
+}
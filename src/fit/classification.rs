@@ -0,0 +1,384 @@
+//! Metrics for evaluating the fitted probabilities of binary responses as a classifier, either on
+//! the training data or on held-out data.
+use super::{Fit, PredictType};
+use crate::{
+    error::{RegressionError, RegressionResult},
+    link::Link,
+    num::Float,
+    response::Response,
+    Logistic,
+};
+use ndarray::{Array1, ArrayBase, Data, Ix1, Ix2};
+
+/// The observed binary responses along with the probabilities predicted for them by a fit, from
+/// which classification metrics are computed. Responses of at least one half are counted as
+/// positive by the metrics that require a binary outcome.
+#[derive(Clone, Debug)]
+pub struct Classification<F> {
+    /// The observed responses, typically zero or one.
+    pub y: Array1<F>,
+    /// The predicted probability of a positive response for each observation.
+    pub prob: Array1<F>,
+}
+
+/// The counts of correct and incorrect classifications at a given probability threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    /// The number of positive responses that are predicted positive.
+    pub true_pos: usize,
+    /// The number of negative responses that are predicted positive.
+    pub false_pos: usize,
+    /// The number of negative responses that are predicted negative.
+    pub true_neg: usize,
+    /// The number of positive responses that are predicted negative.
+    pub false_neg: usize,
+}
+
+impl ConfusionMatrix {
+    /// The fraction of all observations that are classified correctly.
+    pub fn accuracy(&self) -> f64 {
+        let total = self.true_pos + self.false_pos + self.true_neg + self.false_neg;
+        (self.true_pos + self.true_neg) as f64 / total as f64
+    }
+
+    /// The fraction of predicted positives that are actually positive.
+    pub fn precision(&self) -> f64 {
+        self.true_pos as f64 / (self.true_pos + self.false_pos) as f64
+    }
+
+    /// The fraction of actual positives that are predicted positive, also known as the recall or
+    /// the true positive rate.
+    pub fn sensitivity(&self) -> f64 {
+        self.true_pos as f64 / (self.true_pos + self.false_neg) as f64
+    }
+
+    /// The fraction of actual negatives that are predicted negative, or one minus the false
+    /// positive rate.
+    pub fn specificity(&self) -> f64 {
+        self.true_neg as f64 / (self.true_neg + self.false_pos) as f64
+    }
+}
+
+/// The receiver operating characteristic (ROC) curve. Each point gives the false and true
+/// positive rates when predicting a positive response for every probability at or above the
+/// corresponding threshold. The first point, with an infinite threshold, is at the origin.
+#[derive(Clone, Debug)]
+pub struct RocCurve<F> {
+    /// The probability thresholds in decreasing order.
+    pub thresholds: Array1<F>,
+    /// The false positive rate at each threshold.
+    pub fpr: Array1<F>,
+    /// The true positive rate at each threshold.
+    pub tpr: Array1<F>,
+}
+
+/// A binned calibration curve comparing the predicted probabilities to the observed rate of
+/// positive responses. Bins without any observations are omitted.
+#[derive(Clone, Debug)]
+pub struct Calibration<F> {
+    /// The average predicted probability in each bin.
+    pub mean_prob: Array1<F>,
+    /// The average observed response in each bin.
+    pub observed: Array1<F>,
+    /// The number of observations in each bin.
+    pub counts: Vec<usize>,
+}
+
+impl<F> Classification<F>
+where
+    F: Float,
+{
+    fn is_pos(&self, i: usize) -> bool {
+        self.y[i] >= F::from(0.5).unwrap()
+    }
+
+    /// Returns the number of positive and negative responses, or an error if either is zero.
+    fn class_counts(&self) -> RegressionResult<(usize, usize)> {
+        let n_pos = (0..self.y.len()).filter(|&i| self.is_pos(i)).count();
+        let n_neg = self.y.len() - n_pos;
+        if n_pos == 0 || n_neg == 0 {
+            return Err(RegressionError::BadInput(
+                "Both positive and negative responses are required".to_string(),
+            ));
+        }
+        Ok((n_pos, n_neg))
+    }
+
+    /// Returns the indices of the observations in increasing order of their predicted
+    /// probability, or an error if any of the probabilities is NaN.
+    fn prob_order(&self) -> RegressionResult<Vec<usize>> {
+        if self.prob.iter().any(|p| p.is_nan()) {
+            return Err(RegressionError::BadInput(
+                "The predicted probabilities must not be NaN".to_string(),
+            ));
+        }
+        let prob: Vec<f64> = self.prob.iter().map(|p| p.to_f64().unwrap()).collect();
+        let mut order: Vec<usize> = (0..prob.len()).collect();
+        order.sort_by(|&a, &b| prob[a].total_cmp(&prob[b]));
+        Ok(order)
+    }
+
+    /// The area under the ROC curve, computed as the Mann-Whitney U statistic normalized by the
+    /// number of positive-negative pairs. This is the probability that a randomly chosen positive
+    /// observation has a higher predicted probability than a randomly chosen negative one, with
+    /// ties counted as one half.
+    pub fn auc(&self) -> RegressionResult<F> {
+        let (n_pos, n_neg) = self.class_counts()?;
+        let order = self.prob_order()?;
+        // Sum the ranks of the positive observations, using the average rank for ties.
+        let mut rank_sum_pos = 0.;
+        let mut start = 0;
+        while start < order.len() {
+            let mut end = start + 1;
+            while end < order.len() && self.prob[order[end]] == self.prob[order[start]] {
+                end += 1;
+            }
+            let avg_rank = 0.5 * (start + end + 1) as f64;
+            let n_pos_tied = order[start..end]
+                .iter()
+                .filter(|&&i| self.is_pos(i))
+                .count();
+            rank_sum_pos += avg_rank * n_pos_tied as f64;
+            start = end;
+        }
+        let n_pos_f = n_pos as f64;
+        let u_stat = rank_sum_pos - 0.5 * n_pos_f * (n_pos_f + 1.);
+        Ok(F::from(u_stat / (n_pos_f * n_neg as f64)).unwrap())
+    }
+
+    /// The Brier score, the mean squared difference between the predicted probabilities and the
+    /// observed responses.
+    pub fn brier_score(&self) -> F {
+        let diff: Array1<F> = &self.prob - &self.y;
+        diff.mapv_into(|d| d * d).mean().unwrap()
+    }
+
+    /// Returns the binned calibration curve, dividing the probabilities into `n_bins` bins of
+    /// equal width.
+    pub fn calibration(&self, n_bins: usize) -> RegressionResult<Calibration<F>> {
+        if n_bins == 0 {
+            return Err(RegressionError::BadInput(
+                "At least one bin is required".to_string(),
+            ));
+        }
+        let mut sum_prob = vec![F::zero(); n_bins];
+        let mut sum_obs = vec![F::zero(); n_bins];
+        let mut counts = vec![0; n_bins];
+        for (&p, &y) in self.prob.iter().zip(self.y.iter()) {
+            let bin = (p.to_f64().unwrap() * n_bins as f64).floor() as usize;
+            // A probability of exactly one belongs to the last bin.
+            let bin = bin.min(n_bins - 1);
+            sum_prob[bin] += p;
+            sum_obs[bin] += y;
+            counts[bin] += 1;
+        }
+        let filled: Vec<usize> = (0..n_bins).filter(|&b| counts[b] > 0).collect();
+        Ok(Calibration {
+            mean_prob: filled
+                .iter()
+                .map(|&b| sum_prob[b] / F::from(counts[b]).unwrap())
+                .collect(),
+            observed: filled
+                .iter()
+                .map(|&b| sum_obs[b] / F::from(counts[b]).unwrap())
+                .collect(),
+            counts: filled.iter().map(|&b| counts[b]).collect(),
+        })
+    }
+
+    /// Returns the confusion matrix when predicting a positive response for every probability at
+    /// or above the threshold.
+    pub fn confusion(&self, threshold: F) -> ConfusionMatrix {
+        let mut confusion = ConfusionMatrix {
+            true_pos: 0,
+            false_pos: 0,
+            true_neg: 0,
+            false_neg: 0,
+        };
+        for (i, &p) in self.prob.iter().enumerate() {
+            match (p >= threshold, self.is_pos(i)) {
+                (true, true) => confusion.true_pos += 1,
+                (true, false) => confusion.false_pos += 1,
+                (false, false) => confusion.true_neg += 1,
+                (false, true) => confusion.false_neg += 1,
+            }
+        }
+        confusion
+    }
+
+    /// The log-loss, or the negative log-likelihood of the observed responses averaged over the
+    /// observations. This is infinite if any observation has a predicted probability of zero for
+    /// its observed outcome.
+    pub fn log_loss(&self) -> F {
+        let terms: Array1<F> = ndarray::Zip::from(&self.y)
+            .and(&self.prob)
+            .map_collect(|&y, &p| {
+                let mut like = F::zero();
+                if y > F::zero() {
+                    like += y * num_traits::Float::ln(p);
+                }
+                if y < F::one() {
+                    like += (F::one() - y) * num_traits::Float::ln(F::one() - p);
+                }
+                -like
+            });
+        terms.mean().unwrap()
+    }
+
+    /// Returns the ROC curve, with one point for each distinct predicted probability.
+    pub fn roc(&self) -> RegressionResult<RocCurve<F>> {
+        let (n_pos, n_neg) = self.class_counts()?;
+        let mut order = self.prob_order()?;
+        order.reverse();
+        let mut thresholds = vec![F::infinity()];
+        let mut fpr = vec![F::zero()];
+        let mut tpr = vec![F::zero()];
+        let (mut true_pos, mut false_pos) = (0, 0);
+        for (k, &i) in order.iter().enumerate() {
+            if self.is_pos(i) {
+                true_pos += 1;
+            } else {
+                false_pos += 1;
+            }
+            // Only add a point once all observations tied at this threshold are included.
+            if k + 1 == order.len() || self.prob[order[k + 1]] != self.prob[i] {
+                thresholds.push(self.prob[i]);
+                fpr.push(F::from(false_pos as f64 / n_neg as f64).unwrap());
+                tpr.push(F::from(true_pos as f64 / n_pos as f64).unwrap());
+            }
+        }
+        Ok(RocCurve {
+            thresholds: Array1::from(thresholds),
+            fpr: Array1::from(fpr),
+            tpr: Array1::from(tpr),
+        })
+    }
+}
+
+impl<'a, L, F> Fit<'a, Logistic<L>, F>
+where
+    L: Link<Logistic<L>>,
+    F: 'static + Float,
+{
+    /// Returns the fitted probabilities of the training data along with the observed responses,
+    /// for evaluating the fit as a classifier.
+    pub fn classification(&self) -> Classification<F> {
+        Classification {
+            y: self.data.y.clone(),
+            prob: self.predict_design(&self.data.x, self.data.linear_offset.as_ref()),
+        }
+    }
+
+    /// Returns the predicted probabilities of held-out data along with its observed responses,
+    /// for evaluating the fit as a classifier. The covariates are transformed as in `predict()`.
+    pub fn classification_new<Y, S1, S2>(
+        &self,
+        data_y: &ArrayBase<S1, Ix1>,
+        data_x: &ArrayBase<S2, Ix2>,
+        lin_off: Option<&Array1<F>>,
+    ) -> RegressionResult<Classification<F>>
+    where
+        Y: Response<Logistic<L>> + Copy,
+        S1: Data<Elem = Y>,
+        S2: Data<Elem = F>,
+    {
+        if data_y.len() != data_x.nrows() {
+            return Err(RegressionError::BadInput(
+                "y and x data must have same number of points".to_string(),
+            ));
+        }
+        let y: Array1<F> = data_y
+            .iter()
+            .map(|&y| y.into_float())
+            .collect::<Result<_, _>>()?;
+        let prob = self.predict(data_x, lin_off, PredictType::Response)?;
+        Ok(Classification { y, prob })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Classification, ConfusionMatrix};
+    use crate::{model::ModelBuilder, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Axis};
+
+    #[test]
+    fn metrics() -> Result<()> {
+        let class = Classification {
+            y: array![1., 0., 1., 1., 0., 0.],
+            prob: array![0.9, 0.8, 0.6, 0.6, 0.3, 0.1],
+        };
+        // Of the 9 positive-negative pairs, only the two positives at 0.6 are ranked below the
+        // negative at 0.8.
+        assert_abs_diff_eq!(class.auc()?, 7. / 9.);
+        let roc = class.roc()?;
+        assert_abs_diff_eq!(roc.fpr, array![0., 0., 1. / 3., 1. / 3., 2. / 3., 1.]);
+        assert_abs_diff_eq!(roc.tpr, array![0., 1. / 3., 1. / 3., 1., 1., 1.]);
+        assert_eq!(roc.thresholds.len(), 6);
+        // The AUC is also the area under the ROC curve by the trapezoid rule.
+        let area: f64 = (1..roc.fpr.len())
+            .map(|i| 0.5 * (roc.fpr[i] - roc.fpr[i - 1]) * (roc.tpr[i] + roc.tpr[i - 1]))
+            .sum();
+        assert_abs_diff_eq!(area, 7. / 9., epsilon = 1e-12);
+
+        let confusion = class.confusion(0.5);
+        assert_eq!(
+            confusion,
+            ConfusionMatrix {
+                true_pos: 3,
+                false_pos: 1,
+                true_neg: 2,
+                false_neg: 0
+            }
+        );
+        assert_abs_diff_eq!(confusion.accuracy(), 5. / 6.);
+        assert_abs_diff_eq!(confusion.sensitivity(), 1.);
+        assert_abs_diff_eq!(confusion.specificity(), 2. / 3.);
+
+        let brier = (0.01 + 0.64 + 0.16 + 0.16 + 0.09 + 0.01) / 6.;
+        assert_abs_diff_eq!(class.brier_score(), brier, epsilon = 1e-12);
+        let log_loss =
+            -(0.9f64.ln() + 0.2f64.ln() + 2. * 0.6f64.ln() + 0.7f64.ln() + 0.9f64.ln()) / 6.;
+        assert_abs_diff_eq!(class.log_loss(), log_loss, epsilon = 1e-12);
+
+        let calib = class.calibration(2)?;
+        assert_eq!(calib.counts, vec![2, 4]);
+        assert_abs_diff_eq!(calib.mean_prob, array![0.2, 0.725], epsilon = 1e-12);
+        assert_abs_diff_eq!(calib.observed, array![0., 0.75]);
+
+        // The ranking metrics reject undefined probabilities instead of panicking.
+        let class_nan = Classification {
+            prob: array![0.9, f64::NAN, 0.6, 0.6, 0.3, 0.1],
+            ..class
+        };
+        assert!(class_nan.auc().is_err());
+        assert!(class_nan.roc().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn classification_fit() -> Result<()> {
+        let data_y = array![true, false, false, true, true, false, true, true, false, true];
+        let data_x =
+            array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2, -0.8].insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let train = fit.classification();
+        let held_out = fit.classification_new(&data_y, &data_x, None)?;
+        assert_abs_diff_eq!(train.prob, held_out.prob, epsilon = 1e-12);
+        // The log-loss on the training data is the negative average log-likelihood.
+        assert_abs_diff_eq!(
+            train.log_loss(),
+            -fit.model_like / data_y.len() as f64,
+            epsilon = 1e-12
+        );
+        let auc = train.auc()?;
+        assert!(auc > 0.5 && auc <= 1.);
+        assert!(fit
+            .classification_new(&array![true, false], &data_x, None)
+            .is_err());
+        Ok(())
+    }
+}
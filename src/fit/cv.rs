@@ -0,0 +1,322 @@
+//! K-fold cross-validation of the regularization strength.
+use super::{options::FitConfig, Fit};
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::Glm,
+    link::Link,
+    model::{Dataset, Model},
+    num::Float,
+    Logistic,
+};
+use ndarray::{Array1, Array2};
+use rand::{seq::SliceRandom, Rng};
+use std::marker::PhantomData;
+
+/// The measure of the out-of-fold prediction error used in cross-validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CvMeasure {
+    /// The mean deviance of the held-out observations, without any dispersion.
+    Deviance,
+    /// The fraction of held-out observations that are misclassified when the predicted mean is
+    /// thresholded at 0.5. This is only meaningful for binary responses.
+    Misclassification,
+    /// The mean squared error of the predicted means of the held-out observations.
+    MeanSqErr,
+}
+
+/// The results of cross-validating the regularization strength, along with the fits to all of
+/// the data at the selected strengths.
+pub struct CrossValidation<'a, M, F>
+where
+    M: Glm,
+    F: Float,
+{
+    /// The penalty strengths that were cross-validated.
+    pub lambdas: Array1<F>,
+    /// The out-of-fold error of each fold at each penalty strength, with one row per fold.
+    pub fold_errors: Array2<F>,
+    /// The mean out-of-fold error at each penalty strength, weighting the folds by their size.
+    pub cv_mean: Array1<F>,
+    /// The standard error of the mean out-of-fold error at each penalty strength.
+    pub cv_se: Array1<F>,
+    /// The penalty strength with the smallest mean error.
+    pub lambda_min: F,
+    /// The largest penalty strength with a mean error within one standard error of the smallest.
+    pub lambda_1se: F,
+    /// The fit to all of the data at `lambda_min`.
+    pub fit_min: Fit<'a, M, F>,
+    /// The fit to all of the data at `lambda_1se`.
+    pub fit_1se: Fit<'a, M, F>,
+}
+
+impl<'a, M, F> FitConfig<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Cross-validate the penalty strength over the given `lambdas`, with the penalty mixed as
+    /// in `path()`. The fold of each observation is given by `folds`, for instance from
+    /// `kfold()`. For each fold the regularization path is fit with the other observations and
+    /// the error of the held-out observations is measured; then the model is re-fit to all of
+    /// the data at `lambda_min` and `lambda_1se`. Each fit uses the other options of this
+    /// configuration. The covariates of every fold use the standardization of the full model.
+    pub fn cross_validate(
+        self,
+        alpha: F,
+        lambdas: &Array1<F>,
+        folds: &[usize],
+        measure: CvMeasure,
+    ) -> RegressionResult<CrossValidation<'a, M, F>> {
+        let data = &self.model.data;
+        let n_data = data.y.len();
+        if folds.len() != n_data {
+            return Err(RegressionError::BadInput(
+                "Each observation must have a fold label".to_string(),
+            ));
+        }
+        if lambdas.is_empty() {
+            return Err(RegressionError::BadInput(
+                "Cross-validation requires at least one lambda".to_string(),
+            ));
+        }
+        let mut labels: Vec<usize> = folds.to_vec();
+        labels.sort_unstable();
+        labels.dedup();
+        if labels.len() < 2 {
+            return Err(RegressionError::BadInput(
+                "Cross-validation requires at least two folds".to_string(),
+            ));
+        }
+
+        let mut fold_errors = Array2::<F>::zeros((labels.len(), lambdas.len()));
+        let mut fold_sizes = Array1::<F>::zeros(labels.len());
+        for (k, label) in labels.iter().enumerate() {
+            let (test, train): (Vec<usize>, Vec<usize>) =
+                (0..n_data).partition(|&i| folds[i] == *label);
+            let train_model = Model {
+                model: PhantomData::<M>,
+                // The covariates are already standardized and padded.
+                data: data.select_rows(&train),
+                use_intercept: self.model.use_intercept,
+                standardization: None,
+                aliased: self.model.aliased.clone(),
+            };
+            let path = train_model
+                .with_options(self.options.clone())
+                .path_with_lambdas(alpha, lambdas)?;
+            let test_data: Dataset<F> = data.select_rows(&test);
+            for (l, coef) in path.coefficients.rows().into_iter().enumerate() {
+                fold_errors[[k, l]] = oof_error::<M, F>(&test_data, &coef.to_owned(), measure);
+            }
+            fold_sizes[k] = F::from(test.len()).unwrap();
+        }
+
+        let total: F = fold_sizes.sum();
+        let cv_mean: Array1<F> = fold_sizes.dot(&fold_errors) / total;
+        let n_folds_m1 = F::from(labels.len() - 1).unwrap();
+        let cv_se: Array1<F> = (&fold_errors - &cv_mean)
+            .mapv_into(|d| d * d)
+            .t()
+            .dot(&fold_sizes)
+            .mapv_into(|v| num_traits::Float::sqrt(v / total / n_folds_m1));
+
+        let i_min: usize = (0..lambdas.len())
+            .min_by(|&a, &b| cv_mean[a].total_cmp(&cv_mean[b]))
+            .unwrap();
+        let threshold = cv_mean[i_min] + cv_se[i_min];
+        let i_1se: usize = (0..lambdas.len())
+            .filter(|&l| cv_mean[l] <= threshold)
+            .max_by(|&a, &b| lambdas[a].total_cmp(&lambdas[b]))
+            .unwrap_or(i_min);
+
+        let refit = |lambda: F| {
+            let mut options = self.options.clone();
+            options.l1 = alpha * lambda;
+            options.l2 = (F::one() - alpha) * lambda;
+            self.model.with_options(options).fit()
+        };
+        Ok(CrossValidation {
+            lambdas: lambdas.clone(),
+            fold_errors,
+            cv_mean,
+            cv_se,
+            lambda_min: lambdas[i_min],
+            lambda_1se: lambdas[i_1se],
+            fit_min: refit(lambdas[i_min])?,
+            fit_1se: refit(lambdas[i_1se])?,
+        })
+    }
+
+    /// Randomly assign each observation to one of `n_folds` folds of nearly equal size, returning
+    /// the fold label of each observation for use in `cross_validate()`.
+    pub fn kfold<R>(&self, n_folds: usize, rng: &mut R) -> RegressionResult<Vec<usize>>
+    where
+        R: Rng + ?Sized,
+    {
+        let n_data = self.model.data.y.len();
+        check_n_folds(n_folds, n_data)?;
+        let mut order: Vec<usize> = (0..n_data).collect();
+        order.shuffle(rng);
+        Ok(assign_folds(&order, n_folds, n_data))
+    }
+}
+
+impl<'a, L, F> FitConfig<'a, Logistic<L>, F>
+where
+    L: Link<Logistic<L>>,
+    F: 'static + Float,
+{
+    /// Randomly assign each observation to one of `n_folds` folds, stratified so that the
+    /// fraction of positive responses in each fold is as close as possible to that of the whole
+    /// dataset.
+    pub fn kfold_stratified<R>(&self, n_folds: usize, rng: &mut R) -> RegressionResult<Vec<usize>>
+    where
+        R: Rng + ?Sized,
+    {
+        let y = &self.model.data.y;
+        check_n_folds(n_folds, y.len())?;
+        let (mut pos, mut neg): (Vec<usize>, Vec<usize>) =
+            (0..y.len()).partition(|&i| y[i] > F::half());
+        pos.shuffle(rng);
+        neg.shuffle(rng);
+        // Deal the positive and then the negative observations to the folds in turn.
+        pos.append(&mut neg);
+        Ok(assign_folds(&pos, n_folds, y.len()))
+    }
+}
+
+/// Assign the observations in the given order to the folds in turn.
+fn assign_folds(order: &[usize], n_folds: usize, n_data: usize) -> Vec<usize> {
+    let mut folds = vec![0; n_data];
+    for (pos, &i) in order.iter().enumerate() {
+        folds[i] = pos % n_folds;
+    }
+    folds
+}
+
+fn check_n_folds(n_folds: usize, n_data: usize) -> RegressionResult<()> {
+    if n_folds < 2 || n_folds > n_data {
+        return Err(RegressionError::BadInput(format!(
+            "Number of folds must be between 2 and the number of observations, found {n_folds}"
+        )));
+    }
+    Ok(())
+}
+
+/// The mean error of the held-out data given the parameters fit without it.
+fn oof_error<M, F>(data: &Dataset<F>, params: &Array1<F>, measure: CvMeasure) -> F
+where
+    M: Glm,
+    F: Float,
+{
+    let n = F::from(data.y.len()).unwrap();
+    match measure {
+        CvMeasure::Deviance => {
+            let sat: F = data.y.mapv(M::log_like_sat).sum();
+            F::from(2.).unwrap() * (sat - M::log_like(data, params)) / n
+        }
+        CvMeasure::Misclassification => {
+            let mean: Array1<F> = M::mean(&data.linear_predictor(params));
+            let n_wrong = mean
+                .iter()
+                .zip(data.y.iter())
+                .filter(|(&mu, &y)| (mu > F::half()) != (y > F::half()))
+                .count();
+            F::from(n_wrong).unwrap() / n
+        }
+        CvMeasure::MeanSqErr => {
+            let mean: Array1<F> = M::mean(&data.linear_predictor(params));
+            (&data.y - &mean).mapv_into(|r| r * r).sum() / n
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CvMeasure;
+    use crate::{model::ModelBuilder, Linear, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array1, Array2, Axis};
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, StandardNormal};
+
+    #[test]
+    fn cv_lasso_linear() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = 60;
+        let data_x: Array2<f64> =
+            Array2::from_shape_simple_fn((n, 5), || StandardNormal.sample(&mut rng));
+        let noise: Array1<f64> =
+            Array1::from_shape_simple_fn(n, || StandardNormal.sample(&mut rng));
+        // Only the first two covariates are relevant.
+        let data_y: Array1<f64> = data_x.column(0).to_owned() * 2. - data_x.column(1) + noise;
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let lambdas = model.fit_options().lambda_grid(1., 12, 0.01)?;
+        let folds = model.fit_options().kfold(5, &mut rng)?;
+        for k in 0..5 {
+            assert_eq!(folds.iter().filter(|&&f| f == k).count(), 12);
+        }
+        let cv = model.fit_options().max_iter(1024).cross_validate(
+            1.,
+            &lambdas,
+            &folds,
+            CvMeasure::MeanSqErr,
+        )?;
+        assert_eq!(cv.fold_errors.dim(), (5, 12));
+        assert_abs_diff_eq!(
+            cv.cv_mean,
+            cv.fold_errors.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-12
+        );
+        assert!(cv.lambda_1se >= cv.lambda_min);
+        assert!(cv.lambda_min < lambdas[0]);
+        let i_min = lambdas.iter().position(|&l| l == cv.lambda_min).unwrap();
+        assert!(cv.cv_mean.iter().all(|&e| e >= cv.cv_mean[i_min]));
+        // The refit at lambda_min keeps the relevant covariates.
+        assert_abs_diff_eq!(cv.fit_min.options.l1, cv.lambda_min);
+        assert!(cv.fit_min.result[1] > 1.);
+        assert!(cv.fit_min.result[2] < -0.5);
+        // For a linear model the deviance is the squared error.
+        let cv_dev = model.fit_options().max_iter(1024).cross_validate(
+            1.,
+            &lambdas,
+            &folds,
+            CvMeasure::Deviance,
+        )?;
+        assert_abs_diff_eq!(cv_dev.cv_mean, cv.cv_mean, epsilon = 1e-10);
+        assert!(model
+            .fit_options()
+            .cross_validate(1., &lambdas, &vec![0; n], CvMeasure::Deviance)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cv_ridge_logistic() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let n = 40;
+        let data_x: Array2<f64> =
+            Array2::from_shape_simple_fn((n, 2), || StandardNormal.sample(&mut rng));
+        let data_y: Array1<bool> = data_x
+            .rows()
+            .into_iter()
+            .map(|x| {
+                x[0] + 0.5 * x[1] + Distribution::<f64>::sample(&StandardNormal, &mut rng) > 0.
+            })
+            .collect();
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let config = model.fit_options();
+        let folds = config.kfold_stratified(4, &mut rng)?;
+        let n_pos = data_y.iter().filter(|&&y| y).count();
+        for k in 0..4 {
+            let fold_pos = (0..n).filter(|&i| folds[i] == k && data_y[i]).count();
+            assert!(fold_pos * 4 >= n_pos - 3 && fold_pos * 4 <= n_pos + 3);
+        }
+        let lambdas = array![10., 3., 1., 0.3, 0.1];
+        let cv = config.cross_validate(0., &lambdas, &folds, CvMeasure::Misclassification)?;
+        assert!(cv.cv_mean.iter().all(|&e| (0. ..=1.).contains(&e)));
+        assert_abs_diff_eq!(cv.fit_1se.options.l2, cv.lambda_1se);
+        Ok(())
+    }
+}
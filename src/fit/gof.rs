@@ -0,0 +1,322 @@
+//! Goodness-of-fit tests comparing the fitted model to the observed responses.
+use super::{hypothesis::HypothesisTest, Fit};
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::{DispersionType, Glm},
+    link::Link,
+    math::norm_cdf,
+    num::Float,
+    Binomial, Logistic, Poisson,
+};
+use ndarray::Array1;
+
+/// The result of a test for overdispersion relative to the variance assumed by the model.
+#[derive(Clone, Debug)]
+pub struct OverdispersionTest<F> {
+    /// The estimated overdispersion coefficient. Its sign indicates over- (positive) or
+    /// under-dispersion (negative).
+    pub alpha: F,
+    /// The standard error of the overdispersion coefficient.
+    pub std_err: F,
+    /// The test statistic, which is asymptotically standard normal under the null hypothesis of
+    /// no overdispersion.
+    pub z: F,
+    /// The one-sided p-value against the alternative of overdispersion.
+    pub p_value: F,
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Perform the deviance goodness-of-fit test, comparing the model to the saturated model.
+    /// The deviance is tested against a chi-squared distribution with `ndf()` degrees of freedom.
+    /// The approximation is poor when the expected counts are small. The test is only meaningful
+    /// for families with a fixed dispersion, since an estimated dispersion is itself proportional
+    /// to the deviance, so `BadInput` is returned for the others.
+    pub fn deviance_test(&self) -> RegressionResult<HypothesisTest<F>> {
+        self.check_fixed_dispersion("deviance")?;
+        Ok(HypothesisTest::new(self.deviance(), self.ndf()))
+    }
+
+    /// Perform Pearson's chi-squared goodness-of-fit test. The statistic is the sum of the
+    /// squared Pearson residuals, which is tested against a chi-squared distribution with `ndf()`
+    /// degrees of freedom. As with `deviance_test()`, `BadInput` is returned for families with a
+    /// free dispersion.
+    pub fn pearson_test(&self) -> RegressionResult<HypothesisTest<F>> {
+        self.check_fixed_dispersion("Pearson")?;
+        let stat = self.resid_pear().mapv_into(|r| r * r).sum();
+        Ok(HypothesisTest::new(stat, self.ndf()))
+    }
+
+    /// Returns an error naming the test if the family has a free dispersion parameter.
+    fn check_fixed_dispersion(&self, test: &str) -> RegressionResult<()> {
+        match M::DISPERSED {
+            DispersionType::FreeDispersion => Err(RegressionError::BadInput(format!(
+                "The {test} goodness-of-fit test requires a family with a fixed dispersion"
+            ))),
+            DispersionType::NoDispersion => Ok(()),
+        }
+    }
+
+    /// The Hosmer-Lemeshow statistic for a response that counts the successes in `n_trials`
+    /// trials. The observations are sorted by their fitted mean and split into `n_groups` groups
+    /// of nearly equal size. Groups whose fitted probabilities are all zero or all one, as after
+    /// separation, have no variance and are left out along with their degree of freedom.
+    fn hosmer_lemeshow_trials(
+        &self,
+        n_groups: usize,
+        n_trials: F,
+    ) -> RegressionResult<HypothesisTest<F>> {
+        if n_groups < 3 || n_groups > self.n_data {
+            return Err(RegressionError::BadInput(format!(
+                "Number of groups must be between 3 and the number of observations, found {n_groups}"
+            )));
+        }
+        let mean: Array1<F> = M::mean(&self.data.linear_predictor(&self.result));
+        if mean.iter().any(|m| m.is_nan()) {
+            return Err(RegressionError::BadInput(
+                "The fitted probabilities must not be NaN".to_string(),
+            ));
+        }
+        let mut order: Vec<usize> = (0..self.n_data).collect();
+        order.sort_by(|&a, &b| mean[a].partial_cmp(&mean[b]).unwrap());
+        let mut stat = F::zero();
+        let mut n_used: usize = 0;
+        for group in 0..n_groups {
+            let start = group * self.n_data / n_groups;
+            let end = (group + 1) * self.n_data / n_groups;
+            let rows = &order[start..end];
+            let observed: F = rows
+                .iter()
+                .map(|&i| self.data.y[i])
+                .fold(F::zero(), |a, y| a + y);
+            let expected: F = rows.iter().map(|&i| mean[i]).fold(F::zero(), |a, m| a + m);
+            let total: F = F::from(rows.len()).unwrap() * n_trials;
+            let variance: F = expected * (F::one() - expected / total);
+            if variance <= F::epsilon() * total {
+                continue;
+            }
+            let diff = observed - expected;
+            stat += diff * diff / variance;
+            n_used += 1;
+        }
+        if n_used < 3 {
+            return Err(RegressionError::BadInput(format!(
+                "At least 3 groups must have fitted probabilities away from 0 and 1, found {n_used}"
+            )));
+        }
+        Ok(HypothesisTest::new(stat, F::from(n_used - 2).unwrap()))
+    }
+}
+
+impl<'a, L, F> Fit<'a, Logistic<L>, F>
+where
+    L: Link<Logistic<L>>,
+    F: 'static + Float,
+{
+    /// Perform the Hosmer-Lemeshow goodness-of-fit test. The observations are sorted by their
+    /// fitted probability and split into `n_groups` groups of nearly equal size (commonly 10),
+    /// and the observed and expected numbers of successes in each group are compared. The
+    /// statistic is tested against a chi-squared distribution with `n_groups - 2` degrees of
+    /// freedom.
+    /// Groups with fitted probabilities all at 0 or 1 are left out of the statistic and its
+    /// degrees of freedom.
+    pub fn hosmer_lemeshow(&self, n_groups: usize) -> RegressionResult<HypothesisTest<F>> {
+        self.hosmer_lemeshow_trials(n_groups, F::one())
+    }
+}
+
+impl<'a, const N: u16, F> Fit<'a, Binomial<N>, F>
+where
+    F: 'static + Float,
+{
+    /// Perform the Hosmer-Lemeshow goodness-of-fit test. The observations are sorted by their
+    /// fitted probability and split into `n_groups` groups of nearly equal size, and the observed
+    /// and expected numbers of successes in each group are compared. The statistic is tested
+    /// against a chi-squared distribution with `n_groups - 2` degrees of freedom.
+    /// Groups with fitted probabilities all at 0 or 1 are left out of the statistic and its
+    /// degrees of freedom.
+    pub fn hosmer_lemeshow(&self, n_groups: usize) -> RegressionResult<HypothesisTest<F>> {
+        self.hosmer_lemeshow_trials(n_groups, F::from(N).unwrap())
+    }
+}
+
+impl<'a, L, F> Fit<'a, Poisson<L>, F>
+where
+    L: Link<Poisson<L>>,
+    F: 'static + Float,
+{
+    /// Perform the Cameron-Trivedi test for overdispersion, in which the variance is modeled as
+    /// `mu + alpha * mu^2` as in negative binomial regression. The coefficient `alpha` is
+    /// estimated by the auxiliary regression of `((y - mu)^2 - y) / mu` on `mu` without an
+    /// intercept, and its t-statistic is compared to a standard normal distribution.
+    pub fn overdispersion_test(&self) -> RegressionResult<OverdispersionTest<F>> {
+        if self.n_data < 2 {
+            return Err(RegressionError::BadInput(
+                "The overdispersion test requires at least two observations".to_string(),
+            ));
+        }
+        let mean: Array1<F> = Poisson::<L>::mean(&self.data.linear_predictor(&self.result));
+        let resid = &self.data.y - &mean;
+        let aux_y: Array1<F> = (&resid * &resid - &self.data.y) / &mean;
+        let sum_mean_sq = mean.mapv(|m| m * m).sum();
+        let alpha = aux_y.dot(&mean) / sum_mean_sq;
+        let aux_resid: Array1<F> = aux_y - &mean * alpha;
+        let var_aux = aux_resid.mapv(|r| r * r).sum() / F::from(self.n_data - 1).unwrap();
+        let std_err = num_traits::Float::sqrt(var_aux / sum_mean_sq);
+        let z = alpha / std_err;
+        let p_value = F::from(norm_cdf(-z.to_f64().unwrap())).unwrap();
+        Ok(OverdispersionTest {
+            alpha,
+            std_err,
+            z,
+            p_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Binomial, Linear, Logistic, Poisson};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array1, Array2, Axis};
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Gamma};
+
+    #[test]
+    fn pearson_deviance_logistic() -> Result<()> {
+        let data_y = array![true, false, false, true, true, false, true, true, false, true];
+        let data_x =
+            array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2, -0.8].insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let pearson = fit.pearson_test()?;
+        assert_abs_diff_eq!(pearson.statistic, fit.resid_pear().mapv(|r| r * r).sum());
+        assert_eq!(pearson.ndf, 8.);
+        let dev = fit.deviance_test()?;
+        assert_abs_diff_eq!(dev.statistic, fit.deviance());
+        assert!((0. ..=1.).contains(&dev.p_value));
+        Ok(())
+    }
+
+    /// The tests aren't defined for a linear model, whose dispersion is estimated from the
+    /// deviance.
+    #[test]
+    fn deviance_linear() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        assert!(fit.deviance_test().is_err());
+        assert!(fit.pearson_test().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn hosmer_lemeshow() -> Result<()> {
+        let data_y =
+            array![false, false, true, false, true, false, true, true, false, true, true, true];
+        let data_x: Array2<f64> = Array1::linspace(-1., 1., 12).insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let hl = fit.hosmer_lemeshow(4)?;
+        assert_eq!(hl.ndf, 2.);
+        // The covariate is sorted, so the groups are consecutive triplets of observations.
+        let prob = fit.predict(&data_x, None, crate::PredictType::Response)?;
+        let mut stat = 0.;
+        for g in 0..4 {
+            let obs: f64 = (3 * g..3 * g + 3).filter(|&i| data_y[i]).count() as f64;
+            let exp: f64 = (3 * g..3 * g + 3).map(|i| prob[i]).sum();
+            stat += (obs - exp).powi(2) / (exp * (1. - exp / 3.));
+        }
+        assert_abs_diff_eq!(hl.statistic, stat, epsilon = 1e-12);
+        assert!(fit.hosmer_lemeshow(2).is_err());
+        assert!(fit.hosmer_lemeshow(13).is_err());
+
+        // The binomial test with a single trial is identical.
+        let data_y_bin: Array1<u16> = data_y.mapv(u16::from);
+        let model = ModelBuilder::<Binomial<1>>::data(&data_y_bin, &data_x).build()?;
+        let fit_bin = model.fit()?;
+        assert_abs_diff_eq!(
+            fit_bin.hosmer_lemeshow(4)?.statistic,
+            hl.statistic,
+            epsilon = 1e-8
+        );
+        Ok(())
+    }
+
+    /// Groups with fitted probabilities of exactly zero or one are separated from the rest and
+    /// don't contribute to the statistic.
+    #[test]
+    fn hosmer_lemeshow_degenerate() -> Result<()> {
+        let data_y = array![
+            false, false, true, false, true, false, true, true, false, true, true, true, false,
+            false, false
+        ];
+        let data_x: Array2<f64> = Array1::linspace(-1., 1., 15).insert_axis(Axis(1));
+        let offset: Array1<f64> = (0..15)
+            .map(|i| match i {
+                0..=8 => 0.,
+                9..=11 => 100.,
+                _ => -100.,
+            })
+            .collect();
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x)
+            .linear_offset(offset.clone())
+            .build()?;
+        let fit = model.fit()?;
+        let hl = fit.hosmer_lemeshow(5)?;
+        assert!(hl.statistic.is_finite());
+        assert_eq!(hl.ndf, 1.);
+        let prob = fit.predict(&data_x, Some(&offset), crate::PredictType::Response)?;
+        let mut stat = 0.;
+        for g in 0..3 {
+            let obs: f64 = (3 * g..3 * g + 3).filter(|&i| data_y[i]).count() as f64;
+            let exp: f64 = (3 * g..3 * g + 3).map(|i| prob[i]).sum();
+            stat += (obs - exp).powi(2) / (exp * (1. - exp / 3.));
+        }
+        assert_abs_diff_eq!(hl.statistic, stat, epsilon = 1e-12);
+        // Too few groups remain to test the fit if more of the observations are separated.
+        let offset: Array1<f64> = offset
+            .indexed_iter()
+            .map(|(i, &off)| if (6..9).contains(&i) { 100. } else { off })
+            .collect();
+        let data_y: Array1<bool> = data_y
+            .indexed_iter()
+            .map(|(i, &y)| y || (6..9).contains(&i))
+            .collect();
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x)
+            .linear_offset(offset)
+            .build()?;
+        assert!(model.fit()?.hosmer_lemeshow(5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn overdispersion() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = 200;
+        let data_x: Array2<f64> = Array1::linspace(0., 2., n).insert_axis(Axis(1));
+        let mean: Array1<f64> = data_x.column(0).mapv(|x| (0.5 + x).exp());
+        let equi: Array1<u32> =
+            mean.mapv(|mu| rand_distr::Poisson::new(mu).unwrap().sample(&mut rng) as u32);
+        // A gamma-distributed rate with unit mean produces negative binomial counts.
+        let gamma = Gamma::new(2., 0.5)?;
+        let over: Array1<u32> = mean.mapv(|mu| {
+            let rate = mu * gamma.sample(&mut rng);
+            rand_distr::Poisson::new(rate).unwrap().sample(&mut rng) as u32
+        });
+        let model = ModelBuilder::<Poisson>::data(&equi, &data_x).build()?;
+        let test_equi = model.fit()?.overdispersion_test()?;
+        assert!(test_equi.p_value > 0.01);
+        let model = ModelBuilder::<Poisson>::data(&over, &data_x).build()?;
+        let test_over = model.fit()?.overdispersion_test()?;
+        assert!(test_over.p_value < 0.001);
+        // The true overdispersion coefficient is the variance of the gamma rate.
+        assert_abs_diff_eq!(test_over.alpha, 0.5, epsilon = 3. * test_over.std_err);
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+//! General linear hypothesis tests of the form `R * beta = r`, where `R` is a matrix of
+//! contrasts with one row per constraint.
+use super::Fit;
+use crate::{
+    error::{RegressionError, RegressionResult},
+    fit::options::FitOptions,
+    glm::Glm,
+    math::{chi_sq_sf, is_rank_deficient},
+    model::{Dataset, Model},
+    num::Float,
+};
+use ndarray::{concatenate, s, Array1, Array2, Axis};
+use ndarray_linalg::{FactorizeHInto, QRInto, SolveH};
+use std::{cell::RefCell, marker::PhantomData};
+
+/// The result of a hypothesis test with a statistic that is asymptotically chi-squared
+/// distributed.
+#[derive(Clone, Debug)]
+pub struct HypothesisTest<F> {
+    /// The value of the test statistic.
+    pub statistic: F,
+    /// The degrees of freedom of the asymptotic chi-squared distribution of the statistic, equal
+    /// to the number of independent constraints tested. For goodness-of-fit tests these are the
+    /// residual degrees of freedom, which need not be an integer under regularization.
+    pub ndf: F,
+    /// The probability of a statistic at least this large under the null hypothesis.
+    pub p_value: F,
+}
+
+impl<F> HypothesisTest<F>
+where
+    F: Float,
+{
+    pub(crate) fn new(statistic: F, ndf: F) -> Self {
+        Self {
+            statistic,
+            ndf,
+            p_value: chi_sq_sf(statistic, ndf),
+        }
+    }
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Returns the parameters that maximize the likelihood subject to the linear constraints
+    /// `R * beta = r`. The constraints are solved by a re-parameterization in the null space of
+    /// `R`, and the constrained model is fit without regularization.
+    fn constrained_result(
+        &self,
+        r_mat: &Array2<F>,
+        r_vec: &Array1<F>,
+    ) -> RegressionResult<Array1<F>> {
+        let n_cons = check_constraints(r_mat, r_vec, self.n_par)?;
+        // The minimum-norm solution to the constraint equations
+        let beta_0: Array1<F> = r_mat
+            .t()
+            .dot(&r_mat.dot(&r_mat.t()).solveh_into(r_vec.clone())?);
+        if n_cons == self.n_par {
+            // The constraints fully determine the parameters.
+            return Ok(beta_0);
+        }
+        // The trailing columns of the complete Q factor of R^T form an orthonormal basis for the
+        // null space of R. Padding with the identity matrix produces the complete factorization.
+        let padded: Array2<F> = concatenate![Axis(1), r_mat.t(), Array2::eye(self.n_par)];
+        let (q, _) = padded.qr_into()?;
+        let null_basis: Array2<F> = q.slice(s![.., n_cons..]).to_owned();
+        // The fixed part of the parameters is absorbed into the linear offset.
+        let offset: Array1<F> = self.data.linear_predictor(&beta_0);
+        let model = Model {
+            model: PhantomData::<M>,
+            data: Dataset::<F> {
+                y: self.data.y.clone(),
+                x: self.data.x.dot(&null_basis),
+                linear_offset: Some(offset),
+                weights: self.data.weights.clone(),
+                hat: RefCell::new(None),
+            },
+            // The intercept, if any, is mixed into the new covariates.
+            use_intercept: false,
+            standardization: None,
+            aliased: Vec::new(),
+        };
+        // The projection of the unconstrained result is a good starting point.
+        let options = FitOptions {
+            max_iter: self.options.max_iter,
+            tol: self.options.tol,
+            init_guess: Some(null_basis.t().dot(&(&self.result - &beta_0))),
+            ..Default::default()
+        };
+        let constrained_fit = model.with_options(options).fit()?;
+        Ok(beta_0 + null_basis.dot(&constrained_fit.result))
+    }
+
+    /// Perform a likelihood-ratio test of the general linear hypothesis `R * beta = r`, where
+    /// each row of `r_mat` expresses one linear combination of the parameters. The constrained
+    /// model is re-fit without regularization but its likelihood is regularized in the same way
+    /// as this fit, as in `lr_test_against()`. The contrast matrix must have full row rank, and
+    /// the number of degrees of freedom is the number of rows.
+    pub fn lr_test_linear(
+        &self,
+        r_mat: &Array2<F>,
+        r_vec: &Array1<F>,
+    ) -> RegressionResult<HypothesisTest<F>> {
+        let constrained = self.constrained_result(r_mat, r_vec)?;
+        let stat = self.lr_test_against(&constrained);
+        Ok(HypothesisTest::new(stat, F::from(r_mat.nrows()).unwrap()))
+    }
+
+    /// Perform a score test of the general linear hypothesis `R * beta = r`, where each row of
+    /// `r_mat` expresses one linear combination of the parameters. The score and Fisher
+    /// information are evaluated at the constrained maximum-likelihood estimate.
+    pub fn score_test_linear(
+        &self,
+        r_mat: &Array2<F>,
+        r_vec: &Array1<F>,
+    ) -> RegressionResult<HypothesisTest<F>> {
+        let constrained = self.constrained_result(r_mat, r_vec)?;
+        let stat = self.score_test_against(constrained)?;
+        Ok(HypothesisTest::new(stat, F::from(r_mat.nrows()).unwrap()))
+    }
+
+    /// Perform a Wald test of the general linear hypothesis `R * beta = r`, where each row of
+    /// `r_mat` expresses one linear combination of the parameters. For instance, the equality of
+    /// the coefficients `i` and `j` is tested with a single row that is `1` in column `i` and
+    /// `-1` in column `j`, with `r = 0`. As in `wald_test()`, the Fisher information is not
+    /// scaled by the dispersion.
+    pub fn wald_test_linear(
+        &self,
+        r_mat: &Array2<F>,
+        r_vec: &Array1<F>,
+    ) -> RegressionResult<HypothesisTest<F>> {
+        let n_cons = check_constraints(r_mat, r_vec, self.n_par)?;
+        let diff: Array1<F> = r_mat.dot(&self.result) - r_vec;
+        let fisher: Array2<F> = self.fisher(&self.result);
+        // R * I^-1 * R^T, computed without an explicit inverse
+        let fisher_inv_rt: Array2<F> = solveh_mat(fisher, r_mat.t().to_owned())?;
+        let cov_cons: Array2<F> = r_mat.dot(&fisher_inv_rt);
+        let stat = diff.dot(&cov_cons.solveh_into(diff.clone())?);
+        Ok(HypothesisTest::new(stat, F::from(n_cons).unwrap()))
+    }
+}
+
+/// Check the dimensions and rank of the constraints, returning the number of constraints.
+pub(crate) fn check_constraints<F: Float>(
+    r_mat: &Array2<F>,
+    r_vec: &Array1<F>,
+    n_par: usize,
+) -> RegressionResult<usize> {
+    if r_mat.ncols() != n_par {
+        return Err(RegressionError::BadInput(format!(
+            "Contrast matrix must have {n_par} columns, one per parameter"
+        )));
+    }
+    if r_mat.nrows() != r_vec.len() {
+        return Err(RegressionError::BadInput(
+            "Contrast matrix and constraint vector must have the same number of rows".to_string(),
+        ));
+    }
+    if r_mat.nrows() == 0 || is_rank_deficient(r_mat.dot(&r_mat.t()), F::epsilon())? {
+        return Err(RegressionError::BadInput(
+            "Contrast matrix must have full row rank".to_string(),
+        ));
+    }
+    Ok(r_mat.nrows())
+}
+
+/// Solve the symmetric system `A * X = B` column-by-column.
+fn solveh_mat<F: Float>(a: Array2<F>, mut b: Array2<F>) -> RegressionResult<Array2<F>> {
+    let a_fact = a.factorizeh_into()?;
+    for mut col in b.columns_mut() {
+        let sol: Array1<F> = a_fact.solveh(&col.to_owned())?;
+        col.assign(&sol);
+    }
+    Ok(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Linear, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array1, Array2};
+
+    /// Testing every covariate against zero should reproduce the null model tests.
+    #[test]
+    fn linear_hypothesis_null() -> Result<()> {
+        let data_y = array![-0.3, -0.1, 0.0, 0.2, 0.4, 0.5, 0.8, 0.8, 1.1];
+        let data_x = array![
+            [-0.5, 0.1],
+            [-0.2, 0.4],
+            [0.1, -0.3],
+            [0.2, 0.2],
+            [0.5, 0.1],
+            [0.6, -0.4],
+            [0.7, 0.3],
+            [0.9, 0.0],
+            [1.3, -0.2]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let r_mat = array![[0., 1., 0.], [0., 0., 1.]];
+        let r_vec = Array1::<f64>::zeros(2);
+        let lr = fit.lr_test_linear(&r_mat, &r_vec)?;
+        let wald = fit.wald_test_linear(&r_mat, &r_vec)?;
+        let score = fit.score_test_linear(&r_mat, &r_vec)?;
+        assert_eq!(lr.ndf, 2.);
+        assert_abs_diff_eq!(lr.statistic, fit.lr_test(), epsilon = 32.0 * f64::EPSILON);
+        // These should all be equivalent for linear regression.
+        assert_abs_diff_eq!(lr.statistic, wald.statistic, epsilon = 32.0 * f64::EPSILON);
+        assert_abs_diff_eq!(lr.statistic, score.statistic, epsilon = 32.0 * f64::EPSILON);
+        assert_abs_diff_eq!(lr.p_value, wald.p_value, epsilon = 32.0 * f64::EPSILON);
+        Ok(())
+    }
+
+    /// Test the equality of two coefficients in a logistic regression.
+    #[test]
+    fn linear_hypothesis_equality() -> Result<()> {
+        let data_y = array![true, false, true, false, true, false, false, true, true, false];
+        let data_x = array![
+            [0.5, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.2],
+            [0.4, 0.5],
+            [0.6, 0.1],
+            [0.1, 0.4],
+            [-0.2, -0.4],
+            [0.9, 0.3],
+            [0.2, -0.2],
+            [-0.1, 0.7]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let r_mat = array![[0., 1., -1.]];
+        let r_vec = array![0.];
+        let lr = fit.lr_test_linear(&r_mat, &r_vec)?;
+        let wald = fit.wald_test_linear(&r_mat, &r_vec)?;
+        let score = fit.score_test_linear(&r_mat, &r_vec)?;
+        for test in [&lr, &wald, &score] {
+            assert_eq!(test.ndf, 1.);
+            assert!(test.statistic >= 0.);
+            assert!((0. ..=1.).contains(&test.p_value));
+        }
+        // A hypothesis that the fit satisfies exactly should not be rejected at all.
+        let r_exact = array![[0., 1., 0.]];
+        let r_vec_exact = array![fit.result[1]];
+        let lr_exact = fit.lr_test_linear(&r_exact, &r_vec_exact)?;
+        assert_abs_diff_eq!(lr_exact.statistic, 0., epsilon = 1e-8);
+        assert_abs_diff_eq!(lr_exact.p_value, 1., epsilon = 1e-4);
+        Ok(())
+    }
+
+    #[test]
+    fn linear_hypothesis_bad_input() -> Result<()> {
+        let data_y = array![0.3, 1.3, 0.7, 0.2];
+        let data_x = array![[0.1, 0.2], [-0.4, 0.1], [0.2, 0.4], [0.3, -0.2]];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        // wrong number of columns
+        assert!(fit
+            .wald_test_linear(&array![[0., 1.]], &array![0.])
+            .is_err());
+        // redundant constraints
+        let r_mat: Array2<f64> = array![[0., 1., 0.], [0., 2., 0.]];
+        assert!(fit.lr_test_linear(&r_mat, &array![0., 0.]).is_err());
+        Ok(())
+    }
+}
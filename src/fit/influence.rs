@@ -0,0 +1,273 @@
+//! Influence diagnostics that measure the effect of each observation on the fit, either through
+//! the one-step approximation or by exactly re-fitting the model without each observation.
+use super::Fit;
+use crate::{
+    error::RegressionResult,
+    glm::{DispersionType, Glm},
+    link::Transform,
+    model::{Dataset, Model},
+    num::Float,
+};
+use ndarray::{Array1, Array2, Axis};
+use ndarray_linalg::{Determinant, InverseInto};
+use std::marker::PhantomData;
+
+/// Influence measures for each observation in the training data. The change in the parameters
+/// is defined as the full-data result minus the result with the observation left out.
+#[derive(Clone, Debug)]
+pub struct Influence<F> {
+    /// Cook's distance, the change in the parameters scaled by their covariance and divided by
+    /// the number of parameters.
+    pub cooks_distance: Array1<F>,
+    /// The change in the linear predictor of each observation, scaled by its standard error
+    /// estimated without that observation.
+    pub dffits: Array1<F>,
+    /// The change in each parameter, with one row per observation and one column per parameter.
+    pub dfbeta: Array2<F>,
+    /// The changes in the parameters scaled by their standard errors estimated without each
+    /// observation.
+    pub dfbetas: Array2<F>,
+    /// The ratio of the determinant of the parameter covariance matrix without each observation
+    /// to that of the full fit.
+    pub cov_ratio: Array1<F>,
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Returns the influence measures of every observation using the one-step approximation,
+    /// which avoids re-fitting the model. This is exact for linear regression without
+    /// regularization. The measures are based on the leverage, the standardized Pearson
+    /// residuals, and the studentized residuals.
+    pub fn influence(&self) -> RegressionResult<Influence<F>> {
+        let n_par = F::from(self.n_par).unwrap();
+        let hat: Array1<F> = self.data.leverage()?;
+        let omh: Array1<F> = -hat.clone() + F::one();
+        let r_pear_std_sq = self.resid_pear_std()?.mapv_into(|r| r * r);
+        let cooks_distance = r_pear_std_sq * &hat / (&omh * n_par);
+        let dffits = self.resid_student()? * (&hat / &omh).mapv_into(num_traits::Float::sqrt);
+        // The one-step change in the parameters is I^-1 * x_i * s_i / (1 - h_i), where s_i is the
+        // contribution of the observation to the score.
+        let fisher_inv: Array2<F> = self.fisher(&self.result).inv_into()?;
+        let score_terms = self.resid_work() / &omh;
+        let dfbeta: Array2<F> = (&self.data.x * &score_terms.insert_axis(Axis(1))).dot(&fisher_inv);
+        // The dispersion is corrected for the contribution from each point as in
+        // `resid_student()`.
+        let phi_i: Array1<F> = match M::DISPERSED {
+            DispersionType::FreeDispersion => {
+                let r_dev_sq = self.resid_dev().mapv_into(|r| r * r);
+                let dof = self.ndf() - F::one();
+                (-r_dev_sq / &omh + self.deviance()) / dof
+            }
+            DispersionType::NoDispersion => Array1::ones(self.n_data),
+        };
+        let dfbetas = self.scale_dfbeta(&dfbeta, &fisher_inv, &phi_i);
+        let phi = self.dispersion();
+        let cov_ratio =
+            (&phi_i / phi).mapv_into(|r| num_traits::Float::powi(r, self.n_par as i32)) / &omh;
+        Ok(Influence {
+            cooks_distance,
+            dffits,
+            dfbeta,
+            dfbetas,
+            cov_ratio,
+        })
+    }
+
+    /// Returns the influence measures of every observation by re-fitting the model once with
+    /// each observation left out. Each re-fit uses the same options as this fit and starts from
+    /// its result. This is more expensive but more accurate than `influence()` for non-linear
+    /// models.
+    pub fn influence_exact(&self) -> RegressionResult<Influence<F>> {
+        let n_par = F::from(self.n_par).unwrap();
+        let hat: Array1<F> = self.data.leverage()?;
+        let fisher: Array2<F> = self.fisher(&self.result);
+        let fisher_inv: Array2<F> = fisher.clone().inv_into()?;
+        let (_, ln_det_fisher) = fisher.sln_det()?;
+        let phi = self.dispersion();
+
+        let mut cooks_distance = Array1::<F>::zeros(self.n_data);
+        let mut dffits = Array1::<F>::zeros(self.n_data);
+        let mut dfbeta = Array2::<F>::zeros((self.n_data, self.n_par));
+        let mut phi_i = Array1::<F>::ones(self.n_data);
+        let mut cov_ratio = Array1::<F>::zeros(self.n_data);
+        for i in 0..self.n_data {
+            let result_i = self.refit_without(&[i])?;
+            let d_beta: Array1<F> = &self.result - &result_i;
+            let x_i = self.data.x.row(i);
+            if let DispersionType::FreeDispersion = M::DISPERSED {
+                phi_i[i] = self.deviance_without(&result_i, &[i]) / (self.ndf() - F::one());
+            }
+            cooks_distance[i] = d_beta.dot(&fisher.dot(&d_beta)) / (n_par * phi);
+            dffits[i] = x_i.dot(&d_beta) / num_traits::Float::sqrt(phi_i[i] * hat[i]);
+            // The Fisher information without the observation, evaluated at the re-fit result
+            let lin_pred_i: Array1<F> = self.data.linear_predictor(&result_i);
+            let eta_i = lin_pred_i.slice(ndarray::s![i..=i]).to_owned();
+            let var_i = M::variance(M::mean(&eta_i)[0]);
+            let eta_d = M::Link::d_nat_param(&eta_i)[0];
+            let x_outer: Array2<F> = x_i
+                .to_owned()
+                .insert_axis(Axis(1))
+                .dot(&x_i.insert_axis(Axis(0)));
+            let fisher_i: Array2<F> = self.fisher(&result_i) - x_outer * (eta_d * var_i * eta_d);
+            let (_, ln_det_fisher_i) = fisher_i.sln_det()?;
+            cov_ratio[i] = num_traits::Float::powi(phi_i[i] / phi, self.n_par as i32)
+                * num_traits::Float::exp(F::from(ln_det_fisher - ln_det_fisher_i).unwrap());
+            dfbeta.row_mut(i).assign(&d_beta);
+        }
+        let dfbetas = self.scale_dfbeta(&dfbeta, &fisher_inv, &phi_i);
+        Ok(Influence {
+            cooks_distance,
+            dffits,
+            dfbeta,
+            dfbetas,
+            cov_ratio,
+        })
+    }
+
+    /// Returns the deviance of the given parameters over the training data with the given
+    /// observations removed. The regularization is included as in `deviance()`.
+    pub(crate) fn deviance_without(&self, params: &Array1<F>, excluded: &[usize]) -> F {
+        let ll_terms: Array1<F> = M::log_like_terms(self.data, params);
+        let ll_sat: Array1<F> = self.data.y.mapv(M::log_like_sat);
+        let diff: F = (ll_sat - ll_terms)
+            .indexed_iter()
+            .filter(|(i, _)| !excluded.contains(i))
+            .map(|(_, d)| *d)
+            .fold(F::zero(), |acc, d| acc + d);
+        F::from(2.).unwrap() * (diff - self.reg.likelihood(params))
+    }
+
+    /// Re-fit the model with the given observations removed, using the same options as this fit
+    /// and starting from its result. Returns the parameters of the re-fit.
+    pub(crate) fn refit_without(&self, excluded: &[usize]) -> RegressionResult<Array1<F>> {
+        let keep: Vec<usize> = (0..self.n_data).filter(|i| !excluded.contains(i)).collect();
+        self.refit_rows(&keep, &self.data.y)
+    }
+
+    /// Re-fit the model to the given rows of the training data, which may be repeated, with the
+    /// responses taken from `y`. The same options as this fit are used, starting from its result.
+    /// Returns the parameters of the re-fit.
+    pub(crate) fn refit_rows(&self, rows: &[usize], y: &Array1<F>) -> RegressionResult<Array1<F>> {
+        let mut data: Dataset<F> = self.data.select_rows(rows);
+        data.y = y.select(Axis(0), rows);
+        let model = Model {
+            model: PhantomData::<M>,
+            // The covariates are already standardized and padded.
+            data,
+            use_intercept: self.use_intercept,
+            standardization: None,
+            aliased: self.aliased.to_vec(),
+        };
+        let mut options = self.options.clone();
+        options.init_guess = Some(self.result.clone());
+        let refit = model.with_options(options).fit()?;
+        Ok(refit.result)
+    }
+
+    /// Scale the parameter changes by the standard errors of the parameters, using the
+    /// dispersion estimated without each observation.
+    fn scale_dfbeta(
+        &self,
+        dfbeta: &Array2<F>,
+        fisher_inv: &Array2<F>,
+        phi_i: &Array1<F>,
+    ) -> Array2<F> {
+        let par_std: Array1<F> = fisher_inv.diag().mapv(num_traits::Float::sqrt);
+        let obs_std: Array1<F> = phi_i.mapv(num_traits::Float::sqrt);
+        dfbeta / &obs_std.insert_axis(Axis(1)) / &par_std.insert_axis(Axis(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Linear, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::array;
+
+    /// The one-step approximation is exact for linear regression.
+    #[test]
+    fn influence_linear_exact() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2, 2.5];
+        let data_x = array![
+            [0.6, 0.2],
+            [-0.3, 0.4],
+            [0.1, -0.3],
+            [0.4, 0.1],
+            [0.2, 0.6],
+            [0.8, -0.1],
+            [0.5, 0.5],
+            [-0.1, 0.0],
+            [1.4, 0.3]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let approx = fit.influence()?;
+        let exact = fit.influence_exact()?;
+        let eps = 1e-10;
+        assert_abs_diff_eq!(approx.cooks_distance, exact.cooks_distance, epsilon = eps);
+        assert_abs_diff_eq!(approx.dffits, exact.dffits, epsilon = eps);
+        assert_abs_diff_eq!(approx.dfbeta, exact.dfbeta, epsilon = eps);
+        assert_abs_diff_eq!(approx.dfbetas, exact.dfbetas, epsilon = eps);
+        assert_abs_diff_eq!(approx.cov_ratio, exact.cov_ratio, epsilon = eps);
+        // Check Cook's distance against the textbook formula for OLS.
+        let hat = fit.data.leverage()?;
+        let resid = fit.resid_resp();
+        let phi = fit.dispersion();
+        for i in 0..data_y.len() {
+            let omh = 1. - hat[i];
+            let cooks = resid[i] * resid[i] * hat[i] / (3. * phi * omh * omh);
+            assert_abs_diff_eq!(approx.cooks_distance[i], cooks, epsilon = eps);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn influence_logistic() -> Result<()> {
+        let data_y = array![
+            true, false, true, false, true, false, false, true, true, false, true, false, true,
+            false
+        ];
+        let data_x = array![
+            [0.5, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.2],
+            [0.4, 0.5],
+            [0.6, 0.1],
+            [0.1, 0.4],
+            [-0.2, -0.4],
+            [0.9, 0.3],
+            [0.2, -0.2],
+            [-0.1, 0.7],
+            [-0.4, -0.1],
+            [0.7, 0.2],
+            [0.3, 0.3],
+            [-0.5, 0.5]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let approx = fit.influence()?;
+        let exact = fit.influence_exact()?;
+        assert_eq!(approx.dfbeta.dim(), (data_y.len(), 3));
+        assert_eq!(exact.dfbeta.dim(), (data_y.len(), 3));
+        // The exact change in the parameters should be the same as the difference with a
+        // re-fit without the observation.
+        let refit_0 = fit.refit_without(&[0])?;
+        assert_abs_diff_eq!(exact.dfbeta.row(0), &fit.result - &refit_0, epsilon = 1e-12);
+        // The one-step approximation should agree roughly with the exact measures.
+        for i in 0..data_y.len() {
+            assert!(exact.cooks_distance[i] >= 0.);
+            assert!(exact.cov_ratio[i] > 0.);
+            assert_eq!(f64::signum(approx.dffits[i]), f64::signum(exact.dffits[i]));
+            assert_abs_diff_eq!(
+                approx.cooks_distance[i],
+                exact.cooks_distance[i],
+                epsilon = 0.5 * exact.cooks_distance[i] + 0.02
+            );
+        }
+        Ok(())
+    }
+}
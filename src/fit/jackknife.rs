@@ -0,0 +1,203 @@
+//! Jackknife estimates from exact re-fits of the model with each observation or group of
+//! observations left out.
+use super::Fit;
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::{DispersionType, Glm},
+    num::Float,
+};
+use ndarray::{Array1, Array2, Axis};
+
+/// The results of re-fitting the model with each fold of the data left out, where a fold is
+/// either a single observation or a group of observations.
+#[derive(Clone, Debug)]
+pub struct Jackknife<F> {
+    /// The parameters of each re-fit, with one row per fold and one column per parameter.
+    pub fold_results: Array2<F>,
+    /// The jackknife estimate of the bias of the parameters. The bias-corrected parameters are
+    /// `fit.result - bias`.
+    pub bias: Array1<F>,
+    /// The jackknife estimate of the covariance matrix of the parameters.
+    pub covariance: Array2<F>,
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Perform a leave-one-out jackknife by re-fitting the model once without each observation.
+    /// Each re-fit uses the same options as this fit and is warm-started from its result.
+    pub fn jackknife(&self) -> RegressionResult<Jackknife<F>> {
+        let folds: Vec<Vec<usize>> = (0..self.n_data).map(|i| vec![i]).collect();
+        self.jackknife_folds(&folds)
+    }
+
+    /// Perform a grouped (delete-a-group) jackknife, re-fitting the model once without each
+    /// group of observations. The group label of each observation is given in `groups`, and the
+    /// folds are ordered by increasing label.
+    pub fn jackknife_groups(&self, groups: &[usize]) -> RegressionResult<Jackknife<F>> {
+        if groups.len() != self.n_data {
+            return Err(RegressionError::BadInput(
+                "Each observation must have a group label".to_string(),
+            ));
+        }
+        let mut labels: Vec<usize> = groups.to_vec();
+        labels.sort_unstable();
+        labels.dedup();
+        let folds: Vec<Vec<usize>> = labels
+            .iter()
+            .map(|label| {
+                groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, g)| *g == label)
+                    .map(|(i, _)| i)
+                    .collect()
+            })
+            .collect();
+        self.jackknife_folds(&folds)
+    }
+
+    fn jackknife_folds(&self, folds: &[Vec<usize>]) -> RegressionResult<Jackknife<F>> {
+        if folds.len() < 2 {
+            return Err(RegressionError::BadInput(
+                "The jackknife requires at least two folds".to_string(),
+            ));
+        }
+        let mut fold_results = Array2::<F>::zeros((folds.len(), self.n_par));
+        for (fold, mut row) in folds.iter().zip(fold_results.rows_mut()) {
+            row.assign(&self.refit_without(fold)?);
+        }
+        let n_folds = F::from(folds.len()).unwrap();
+        let n_folds_m1 = n_folds - F::one();
+        let mean: Array1<F> = fold_results.mean_axis(Axis(0)).unwrap();
+        let bias: Array1<F> = (&mean - &self.result) * n_folds_m1;
+        let centered: Array2<F> = &fold_results - &mean.insert_axis(Axis(0));
+        let covariance: Array2<F> = centered.t().dot(&centered) * (n_folds_m1 / n_folds);
+        Ok(Jackknife {
+            fold_results,
+            bias,
+            covariance,
+        })
+    }
+
+    /// Return the exact studentized residuals, the signed square roots of the change in the
+    /// deviance resulting from leaving each observation out, computed by re-fitting the model
+    /// without each observation. For families with a free dispersion, the change is scaled by the
+    /// dispersion estimated without the observation. `resid_student()` is a cheaper one-step
+    /// approximation to this, which is exact for linear regression.
+    pub fn resid_student_exact(&self) -> RegressionResult<Array1<F>> {
+        let dev = self.deviance();
+        let signs = self.resid_resp().mapv_into(F::signum);
+        let mut resid = Array1::<F>::zeros(self.n_data);
+        for (i, r) in resid.iter_mut().enumerate() {
+            let result_i = self.refit_without(&[i])?;
+            let dev_i = self.deviance_without(&result_i, &[i]);
+            let dev_diff = dev - dev_i;
+            let dev_diff_scaled = match M::DISPERSED {
+                DispersionType::FreeDispersion => {
+                    let phi_i = dev_i / (self.ndf() - F::one());
+                    dev_diff / phi_i
+                }
+                DispersionType::NoDispersion => dev_diff,
+            };
+            // Round-off could make the change slightly negative for points with no influence.
+            *r = num_traits::Float::sqrt(num_traits::Float::max(dev_diff_scaled, F::zero()));
+        }
+        Ok(signs * resid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Linear, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array1, Array2, Axis};
+
+    #[test]
+    fn jackknife_linear() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2, 2.5];
+        let data_x = array![
+            [0.6, 0.2],
+            [-0.3, 0.4],
+            [0.1, -0.3],
+            [0.4, 0.1],
+            [0.2, 0.6],
+            [0.8, -0.1],
+            [0.5, 0.5],
+            [-0.1, 0.0],
+            [1.4, 0.3]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        // The one-step studentized residuals are exact for linear regression.
+        assert_abs_diff_eq!(
+            fit.resid_student_exact()?,
+            fit.resid_student()?,
+            epsilon = 1e-10
+        );
+        // The jackknife should follow from the exact change in parameters, which are also exact in
+        // the one-step approximation for linear regression.
+        let jack = fit.jackknife()?;
+        let n = data_y.len() as f64;
+        let dfbeta = fit.influence()?.dfbeta;
+        assert_abs_diff_eq!(jack.fold_results, &fit.result - &dfbeta, epsilon = 1e-10);
+        let mean_dfbeta = dfbeta.mean_axis(Axis(0)).unwrap();
+        assert_abs_diff_eq!(jack.bias, -(n - 1.) * &mean_dfbeta, epsilon = 1e-10);
+        let centered: Array2<f64> = &dfbeta - &mean_dfbeta;
+        let cov = centered.t().dot(&centered) * ((n - 1.) / n);
+        assert_abs_diff_eq!(jack.covariance, cov, epsilon = 1e-10);
+        // Groups of one observation each reproduce the leave-one-out jackknife, regardless of the
+        // values of the labels.
+        let groups: Vec<usize> = (0..data_y.len()).map(|i| 2 * i + 1).collect();
+        let jack_groups = fit.jackknife_groups(&groups)?;
+        assert_abs_diff_eq!(jack_groups.covariance, jack.covariance, epsilon = 1e-12);
+        // Leaving out groups of several observations
+        let jack_pairs = fit.jackknife_groups(&[0, 0, 1, 1, 2, 2, 3, 3, 3])?;
+        assert_eq!(jack_pairs.fold_results.dim(), (4, 3));
+        assert!(fit.jackknife_groups(&[0, 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn resid_student_exact_logistic() -> Result<()> {
+        let data_y = array![
+            true, false, true, false, true, false, false, true, true, false, true, false, true,
+            false
+        ];
+        let data_x = array![
+            [0.5, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.2],
+            [0.4, 0.5],
+            [0.6, 0.1],
+            [0.1, 0.4],
+            [-0.2, -0.4],
+            [0.9, 0.3],
+            [0.2, -0.2],
+            [-0.1, 0.7],
+            [-0.4, -0.1],
+            [0.7, 0.2],
+            [0.3, 0.3],
+            [-0.5, 0.5]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let exact: Array1<f64> = fit.resid_student_exact()?;
+        let approx = fit.resid_student()?;
+        for (r_ex, r_ap) in exact.iter().zip(approx.iter()) {
+            assert_eq!(r_ex.signum(), r_ap.signum());
+            assert_abs_diff_eq!(*r_ex, *r_ap, epsilon = 0.2 * r_ex.abs());
+        }
+        let jack = fit.jackknife()?;
+        assert_eq!(jack.fold_results.dim(), (data_y.len(), 3));
+        // The jackknife variance is typically a bit more conservative than the Fisher estimate.
+        let cov = fit.covariance()?;
+        for i in 0..3 {
+            assert!(jack.covariance[[i, i]] > 0.5 * cov[[i, i]]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Marginal effects and elasticities of the expected response with respect to each covariate,
+//! with standard errors by the delta method.
+use super::Fit;
+use crate::{error::RegressionResult, glm::Glm, num::Float};
+use ndarray::{s, Array1, Array2, Axis};
+
+/// Where the marginal effects are evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarginType {
+    /// Average the effect over every observation of the training data.
+    Average,
+    /// Evaluate the effect once at the mean of the covariates of the training data.
+    AtMean,
+}
+
+/// Marginal effects or elasticities for each covariate, not including the intercept or any
+/// covariates dropped as aliased.
+#[derive(Clone, Debug)]
+pub struct MarginalEffects<F> {
+    /// The value of the effect for each covariate.
+    pub effects: Array1<F>,
+    /// The standard error of each effect, computed by the delta method from the covariance of
+    /// the parameters.
+    pub std_err: Array1<F>,
+}
+
+impl<'a, M, F> Fit<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Returns the elasticity of the expected response with respect to each covariate, the
+    /// relative change in the mean per relative change in the covariate:
+    /// `d mu / d x_j * x_j / mu`. These are either averaged over the training data or evaluated
+    /// at the mean of the covariates.
+    pub fn elasticities(&self, margin: MarginType) -> RegressionResult<MarginalEffects<F>> {
+        let x_raw = self.covariates_raw();
+        self.delta_method(|params| {
+            let slopes = self.covariate_slopes(params);
+            match margin {
+                MarginType::Average => {
+                    let lin_pred: Array1<F> = self.data.linear_predictor(params);
+                    let ratio = M::d_mean(&lin_pred) / M::mean(&lin_pred);
+                    let elast: Array2<F> = &x_raw * &ratio.insert_axis(Axis(1)) * &slopes;
+                    elast.mean_axis(Axis(0)).unwrap()
+                }
+                MarginType::AtMean => {
+                    let lin_pred = self.lin_pred_at_mean(params);
+                    let ratio = M::d_mean(&lin_pred)[0] / M::mean(&lin_pred)[0];
+                    let x_mean: Array1<F> = x_raw.mean_axis(Axis(0)).unwrap();
+                    x_mean * slopes * ratio
+                }
+            }
+        })
+    }
+
+    /// Returns the marginal effect of each covariate on the expected response,
+    /// `d mu / d x_j = d mu / d eta * beta_j`, with respect to the covariates as they were given
+    /// to the model before any internal standardization. These are either averaged over the
+    /// training data (AME) or evaluated at the mean of the covariates (MEM).
+    pub fn marginal_effects(&self, margin: MarginType) -> RegressionResult<MarginalEffects<F>> {
+        self.delta_method(|params| {
+            let slopes = self.covariate_slopes(params);
+            let d_mean: F = match margin {
+                MarginType::Average => M::d_mean(&self.data.linear_predictor(params))
+                    .mean()
+                    .unwrap(),
+                MarginType::AtMean => M::d_mean(&self.lin_pred_at_mean(params))[0],
+            };
+            slopes * d_mean
+        })
+    }
+
+    /// The derivative of the linear predictor with respect to each covariate as given to the
+    /// model, accounting for any internal standardization.
+    fn covariate_slopes(&self, params: &Array1<F>) -> Array1<F> {
+        let start = usize::from(self.use_intercept);
+        let slopes: Array1<F> = params.slice(s![start..]).to_owned();
+        match self.standardization {
+            Some(stand) => slopes / &stand.scale.select(Axis(0), &self.kept_covariates()),
+            None => slopes,
+        }
+    }
+
+    /// The covariates of the training data as given to the model, without the intercept column
+    /// and with any internal standardization undone.
+    fn covariates_raw(&self) -> Array2<F> {
+        let start = usize::from(self.use_intercept);
+        let x: Array2<F> = self.data.x.slice(s![.., start..]).to_owned();
+        match self.standardization {
+            Some(stand) => {
+                let kept = self.kept_covariates();
+                x * &stand.scale.select(Axis(0), &kept) + &stand.mean.select(Axis(0), &kept)
+            }
+            None => x,
+        }
+    }
+
+    /// The indices of the covariates, not counting the intercept, that were not dropped as
+    /// aliased.
+    fn kept_covariates(&self) -> Vec<usize> {
+        let start = usize::from(self.use_intercept);
+        self.kept_columns()
+            .into_iter()
+            .filter(|&j| j >= start)
+            .map(|j| j - start)
+            .collect()
+    }
+
+    /// The linear predictor at the mean of the covariates and offsets of the training data, as a
+    /// single-element array.
+    fn lin_pred_at_mean(&self, params: &Array1<F>) -> Array1<F> {
+        let x_mean: Array1<F> = self.data.x.mean_axis(Axis(0)).unwrap();
+        let off_mean: F = match &self.data.linear_offset {
+            Some(off) => off.mean().unwrap(),
+            None => F::zero(),
+        };
+        Array1::from_elem(1, x_mean.dot(params) + off_mean)
+    }
+
+    /// Evaluate a function of the parameters at the fit result along with its standard errors
+    /// by the delta method. The Jacobian is computed numerically by central differences.
+    fn delta_method<G>(&self, func: G) -> RegressionResult<MarginalEffects<F>>
+    where
+        G: Fn(&Array1<F>) -> Array1<F>,
+    {
+        let effects = func(&self.result);
+        let step_scale = num_traits::Float::cbrt(F::epsilon());
+        let mut jacobian = Array2::<F>::zeros((effects.len(), self.n_par));
+        for k in 0..self.n_par {
+            let step = step_scale
+                * num_traits::Float::max(F::one(), num_traits::Float::abs(self.result[k]));
+            let mut params = self.result.clone();
+            params[k] += step;
+            let upper = func(&params);
+            params[k] = self.result[k] - step;
+            let lower = func(&params);
+            jacobian
+                .column_mut(k)
+                .assign(&((upper - lower) / (step + step)));
+        }
+        let cov = self.covariance()?;
+        let var: Array1<F> = (&jacobian.dot(&*cov) * &jacobian).sum_axis(Axis(1));
+        Ok(MarginalEffects {
+            effects,
+            std_err: var.mapv_into(num_traits::Float::sqrt),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarginType;
+    use crate::{model::ModelBuilder, Linear, Logistic, Poisson};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, s, Array1, Axis};
+
+    /// The marginal effects of a linear model are its slopes.
+    #[test]
+    fn margins_linear() -> Result<()> {
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![
+            [0.6, 0.2],
+            [2.1, 0.4],
+            [0.4, -0.3],
+            [-3.2, 0.1],
+            [0.7, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.5],
+            [0.5, 0.0]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let cov = fit.covariance()?;
+        for margin in [MarginType::Average, MarginType::AtMean] {
+            let me = fit.marginal_effects(margin)?;
+            assert_abs_diff_eq!(me.effects, fit.result.slice(s![1..]), epsilon = 1e-12);
+            for j in 0..2 {
+                assert_abs_diff_eq!(
+                    me.std_err[j],
+                    f64::sqrt(cov[[j + 1, j + 1]]),
+                    epsilon = 1e-8
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn margins_logistic() -> Result<()> {
+        let data_y = array![true, false, false, true, true, false, true, true, false, true];
+        let data_x =
+            array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5, 1.2, -0.8].insert_axis(Axis(1));
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let ame = fit.marginal_effects(MarginType::Average)?;
+        let prob = fit.predict(&data_x, None, crate::PredictType::Response)?;
+        let d_prob: Array1<f64> = prob.mapv(|p| p * (1. - p));
+        assert_abs_diff_eq!(ame.effects[0], d_prob.mean().unwrap() * fit.result[1]);
+        let x_mean = data_x.mean().unwrap();
+        let p_mean = 1. / (1. + f64::exp(-fit.result[0] - fit.result[1] * x_mean));
+        let mem = fit.marginal_effects(MarginType::AtMean)?;
+        assert_abs_diff_eq!(
+            mem.effects[0],
+            p_mean * (1. - p_mean) * fit.result[1],
+            epsilon = 1e-12
+        );
+        assert!(ame.std_err[0] > 0.);
+
+        // The effects are with respect to the unstandardized covariates.
+        let model_std = ModelBuilder::<Logistic>::data(&data_y, &data_x)
+            .standardize()
+            .build()?;
+        let fit_std = model_std.fit()?;
+        let ame_std = fit_std.marginal_effects(MarginType::Average)?;
+        assert_abs_diff_eq!(ame_std.effects, ame.effects, epsilon = 1e-8);
+        assert_abs_diff_eq!(ame_std.std_err, ame.std_err, epsilon = 1e-6);
+        Ok(())
+    }
+
+    /// With a log link, the elasticity of each covariate is its slope times its value.
+    #[test]
+    fn elasticities_poisson() -> Result<()> {
+        let data_y = array![1u32, 4, 0, 3, 2, 1, 0, 2];
+        let data_x = array![0.6, 2.1, 0.4, -3.2, 0.7, 0.1, -0.3, 0.5].insert_axis(Axis(1));
+        let model = ModelBuilder::<Poisson>::data(&data_y, &data_x).build()?;
+        let fit = model.fit()?;
+        let x_mean = data_x.mean().unwrap();
+        for margin in [MarginType::Average, MarginType::AtMean] {
+            let elast = fit.elasticities(margin)?;
+            assert_abs_diff_eq!(elast.effects[0], fit.result[1] * x_mean, epsilon = 1e-12);
+            let std_err = f64::sqrt(fit.covariance()?[[1, 1]]) * f64::abs(x_mean);
+            assert_abs_diff_eq!(elast.std_err[0], std_err, epsilon = 1e-8);
+        }
+        Ok(())
+    }
+}
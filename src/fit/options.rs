@@ -1,6 +1,13 @@
 //! Fit-specific configuration and fit builder
-use super::Fit;
-use crate::{error::RegressionResult, glm::Glm, model::Model, num::Float, Array1};
+use super::{hypothesis::check_constraints, Fit};
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::Glm,
+    model::Model,
+    num::Float,
+    regularization::NonConvex,
+    Array1, Array2,
+};
 
 /// A builder struct for fit configuration
 pub struct FitConfig<'a, M, F>
@@ -18,6 +25,12 @@ where
     F: Float,
 {
     pub fn fit(self) -> RegressionResult<Fit<'a, M, F>> {
+        self.check_penalty_factors()?;
+        self.check_groups()?;
+        self.check_non_convex()?;
+        self.check_tikhonov()?;
+        self.check_bounds()?;
+        self.check_linear_constraints()?;
         M::regression(self.model, self.options)
     }
 
@@ -43,9 +56,352 @@ where
         self.options.l1 = l1;
         self
     }
+
+    /// Set the solver used for L1 and elastic net regularization
+    pub fn solver(mut self, solver: Solver) -> Self {
+        self.options.solver = solver;
+        self
+    }
+
+    /// Scale the L1 and L2 penalties of each covariate by a factor, with one non-negative factor
+    /// per column of the covariate data, not including the intercept. A factor of zero leaves
+    /// the covariate unpenalized, so that it is not shrunk. The intercept is never penalized.
+    pub fn penalty_factors(mut self, factors: Array1<F>) -> Self {
+        self.options.penalty_factors = Some(factors);
+        self
+    }
+
+    /// Use a group lasso penalty of strength `lambda` on the L2-norm of each group of covariates,
+    /// with one group label per column of the covariate data, not including the intercept.
+    /// Covariates with the same label enter or leave the model together, as for the dummy
+    /// columns of a categorical variable. Each group is penalized in proportion to the square
+    /// root of its size. Combined with `l1_reg()` this is the sparse group lasso, and with
+    /// `l2_reg()` an additional ridge penalty. The penalty factors do not apply to the groups,
+    /// and group penalties are always solved with ADMM. They cannot be combined with `scad()` or
+    /// `mcp()`.
+    pub fn group_reg(mut self, lambda: F, groups: Vec<usize>) -> Self {
+        self.options.group_l = lambda;
+        self.options.groups = Some(groups);
+        self
+    }
+
+    /// Weight the L1 penalty for the adaptive lasso of Zou, by first fitting the model with an L2
+    /// penalty of `init_l2`, which may be zero for an unpenalized fit, and then scaling the L1
+    /// penalty of each covariate by `1 / |beta_init|^gamma`. The weights are computed once here,
+    /// so the returned configuration can be used with `l1_reg()` to fit the model or along a
+    /// regularization path. Any L2 penalty is not reweighted. Any penalty factors apply to the
+    /// initial fit and continue to scale both penalties. Covariates with an initial estimate of
+    /// zero are penalized by a large but finite weight.
+    pub fn adaptive_lasso(mut self, gamma: F, init_l2: F) -> RegressionResult<Self> {
+        if !(gamma > F::zero() && gamma.is_finite()) {
+            return Err(RegressionError::BadInput(format!(
+                "The adaptive lasso exponent must be positive, found {gamma}"
+            )));
+        }
+        if !(init_l2 >= F::zero() && init_l2.is_finite()) {
+            return Err(RegressionError::BadInput(format!(
+                "The initial L2 penalty must be finite and non-negative, found {init_l2}"
+            )));
+        }
+        let init_options = FitOptions {
+            max_iter: self.options.max_iter,
+            tol: self.options.tol,
+            l2: init_l2,
+            penalty_factors: self.options.penalty_factors.clone(),
+            ..Default::default()
+        };
+        let init_fit = self.model.with_options(init_options).fit()?;
+        let start = usize::from(self.model.use_intercept);
+        let init_coef = init_fit.coefficients();
+        let weights: Array1<F> = init_coef
+            .iter()
+            .skip(start)
+            .map(|&b| {
+                // Aliased columns are dropped from the fit, so their weight doesn't matter.
+                if b.is_nan() {
+                    return F::one();
+                }
+                let b_abs = num_traits::Float::max(num_traits::Float::abs(b), F::epsilon());
+                F::one() / num_traits::Float::powf(b_abs, gamma)
+            })
+            .collect();
+        self.options.l1_weights = Some(weights);
+        Ok(self)
+    }
+
+    /// Add the generalized ridge (Tikhonov) penalty `beta^T * P * beta / 2` with a symmetric
+    /// positive semi-definite matrix `P` over the covariates, not including the intercept. For
+    /// instance, `P = lambda * D^T * D` for the difference matrix `D` penalizes the roughness of
+    /// ordered coefficients. The penalty adds to any L2 penalty from `l2_reg()`, and it cannot be
+    /// combined with L1 or group penalties.
+    pub fn penalty_matrix(mut self, pen_mat: Array2<F>) -> Self {
+        self.options.penalty_matrix = Some(pen_mat);
+        self
+    }
+
+    /// Center the L2 and Tikhonov penalties at a prior mean of the covariate coefficients, not
+    /// including the intercept, so that the fit is shrunk towards it instead of towards zero.
+    /// The null model is penalized accordingly in the likelihood-ratio test.
+    pub fn prior_mean(mut self, prior_mean: Array1<F>) -> Self {
+        self.options.prior_mean = Some(prior_mean);
+        self
+    }
+
+    /// Check that the Tikhonov penalty matrix and prior mean, if any, match the covariates, that
+    /// the matrix is symmetric, and that they are not combined with an L1 penalty.
+    fn check_tikhonov(&self) -> RegressionResult<()> {
+        let opts = &self.options;
+        if opts.penalty_matrix.is_none() && opts.prior_mean.is_none() {
+            return Ok(());
+        }
+        let n_cov = self.n_covariates();
+        if let Some(pen_mat) = &opts.penalty_matrix {
+            if pen_mat.dim() != (n_cov, n_cov) {
+                return Err(RegressionError::BadInput(format!(
+                    "Expected a {n_cov}x{n_cov} penalty matrix, found {:?}",
+                    pen_mat.dim()
+                )));
+            }
+            let scale = pen_mat.fold(F::zero(), |acc, &p| {
+                num_traits::Float::max(acc, num_traits::Float::abs(p))
+            });
+            let sym_tol = F::from(n_cov).unwrap() * F::epsilon() * scale;
+            let symmetric = (pen_mat - &pen_mat.t())
+                .iter()
+                .all(|&d| num_traits::Float::abs(d) <= sym_tol);
+            if !symmetric {
+                return Err(RegressionError::BadInput(
+                    "The penalty matrix must be symmetric".to_string(),
+                ));
+            }
+        }
+        if let Some(prior_mean) = &opts.prior_mean {
+            if prior_mean.len() != n_cov {
+                return Err(RegressionError::BadInput(format!(
+                    "Expected a prior mean of length {n_cov}, found {}",
+                    prior_mean.len()
+                )));
+            }
+        }
+        if opts.l1 > F::zero() || (opts.groups.is_some() && opts.group_l > F::zero()) {
+            return Err(RegressionError::BadInput(
+                "Tikhonov penalties cannot be combined with L1 or group penalties".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Constrain the coefficient of each covariate, not including the intercept, to lie between
+    /// the corresponding lower and upper bounds. Either bound may be infinite to leave that side
+    /// unconstrained, and a covariate can be fixed by equal bounds. The constrained fit is solved
+    /// by projected coordinate descent, which may be combined with L1 and L2 penalties but not
+    /// with the other penalties. The bounds that are active at the result are reported by
+    /// `Fit::active_bounds()`.
+    pub fn bounds(mut self, lower: Array1<F>, upper: Array1<F>) -> Self {
+        self.options.lower_bounds = Some(lower);
+        self.options.upper_bounds = Some(upper);
+        self
+    }
+
+    /// Constrain the coefficients of all covariates to be non-negative. This overrides any
+    /// bounds set by `bounds()`.
+    pub fn non_negative(self) -> Self {
+        let n_cov = self.n_covariates();
+        self.bounds(
+            Array1::zeros(n_cov),
+            Array1::from_elem(n_cov, F::infinity()),
+        )
+    }
+
+    /// Check that the bounds, if any, match the covariates and are consistent with each other
+    /// and with the penalties.
+    fn check_bounds(&self) -> RegressionResult<()> {
+        let opts = &self.options;
+        let (lower, upper) = match (&opts.lower_bounds, &opts.upper_bounds) {
+            (Some(lower), Some(upper)) => (lower, upper),
+            (None, None) => return Ok(()),
+            _ => {
+                return Err(RegressionError::BadInput(
+                    "Both lower and upper bounds must be given".to_string(),
+                ))
+            }
+        };
+        let n_cov = self.n_covariates();
+        if lower.len() != n_cov || upper.len() != n_cov {
+            return Err(RegressionError::BadInput(format!(
+                "Expected {n_cov} lower and upper bounds, found {} and {}",
+                lower.len(),
+                upper.len()
+            )));
+        }
+        if !lower.iter().zip(upper.iter()).all(|(&lo, &hi)| lo <= hi) {
+            return Err(RegressionError::BadInput(
+                "Each lower bound must be no greater than the upper bound".to_string(),
+            ));
+        }
+        if (opts.groups.is_some() && opts.group_l > F::zero())
+            || opts.non_convex.is_some()
+            || opts.penalty_matrix.is_some()
+            || opts.prior_mean.is_some()
+        {
+            return Err(RegressionError::BadInput(
+                "Bounds can only be combined with L1 and L2 penalties".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Constrain the parameters to satisfy the linear equality constraints `C * beta = d`, where
+    /// each row of `c_mat` is one linear combination of the parameters as in `Fit::result`,
+    /// including the intercept if it is used. For instance, a row with ones in the columns of an
+    /// effect coding constrains its coefficients to sum to zero. The constraints must be
+    /// linearly independent. Each IRLS step solves the Karush-Kuhn-Tucker system of the
+    /// constraints, and each constraint removes one degree of freedom from the fit. They cannot be
+    /// combined with bounds or with the coordinate descent solver for L1 penalties. A design
+    /// that is identified only through the constraints, such as an intercept with a full set of
+    /// dummy columns, must be built with a negative `colinear_tol` to skip the rank check.
+    pub fn constraints(mut self, c_mat: Array2<F>, d_vec: Array1<F>) -> Self {
+        self.options.constraint_mat = Some(c_mat);
+        self.options.constraint_vec = Some(d_vec);
+        self
+    }
+
+    /// Check that the linear equality constraints, if any, are well-formed and compatible with
+    /// the solver.
+    fn check_linear_constraints(&self) -> RegressionResult<()> {
+        let opts = &self.options;
+        let (c_mat, d_vec) = match (&opts.constraint_mat, &opts.constraint_vec) {
+            (Some(c_mat), Some(d_vec)) => (c_mat, d_vec),
+            (None, None) => return Ok(()),
+            _ => {
+                return Err(RegressionError::BadInput(
+                    "Both the constraint matrix and vector must be given".to_string(),
+                ))
+            }
+        };
+        check_constraints(c_mat, d_vec, self.model.data.x.ncols())?;
+        if opts.lower_bounds.is_some()
+            || (opts.l1 > F::zero() && opts.solver == Solver::CoordinateDescent)
+        {
+            return Err(RegressionError::BadInput(
+                "Linear constraints cannot be combined with bounds or coordinate descent"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replace the L1 penalty set by `l1_reg()` with the smoothly clipped absolute deviation
+    /// (SCAD) penalty of the same strength, with the concavity parameter `gamma > 2`. The penalty
+    /// factors and any L2 penalty apply as with the L1 penalty, including along a regularization
+    /// path. Non-convex penalties are always solved with ADMM, so they cannot be combined with
+    /// the coordinate descent solver, and the fit may converge to a local optimum that depends on
+    /// the initial guess.
+    pub fn scad(mut self, gamma: F) -> Self {
+        self.options.non_convex = Some(NonConvex::Scad(gamma));
+        self
+    }
+
+    /// Replace the L1 penalty set by `l1_reg()` with the minimax concave penalty (MCP) of the
+    /// same strength, with the concavity parameter `gamma > 1`. This is otherwise the same as
+    /// `scad()`.
+    pub fn mcp(mut self, gamma: F) -> Self {
+        self.options.non_convex = Some(NonConvex::Mcp(gamma));
+        self
+    }
+
+    /// Check that the concavity parameter of a non-convex penalty, if any, is finite and in range,
+    /// and that the penalty is not combined with the coordinate descent solver.
+    fn check_non_convex(&self) -> RegressionResult<()> {
+        let penalty = match &self.options.non_convex {
+            Some(penalty) => penalty,
+            None => return Ok(()),
+        };
+        if !(penalty.gamma() > penalty.min_gamma() && penalty.gamma().is_finite()) {
+            return Err(RegressionError::BadInput(format!(
+                "The concavity parameter must be greater than {}, found {}",
+                penalty.min_gamma(),
+                penalty.gamma()
+            )));
+        }
+        if self.options.solver == Solver::CoordinateDescent {
+            return Err(RegressionError::BadInput(
+                "SCAD and MCP penalties are solved with ADMM, not coordinate descent".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The number of covariates in the data, not including the intercept but including any
+    /// columns dropped as aliased.
+    fn n_covariates(&self) -> usize {
+        self.model.data.x.ncols() + self.model.aliased.len() - usize::from(self.model.use_intercept)
+    }
+
+    /// Check that the group labels, if any, match the covariates, and that group penalties are
+    /// not combined with a non-convex penalty.
+    fn check_groups(&self) -> RegressionResult<()> {
+        let n_cov = self.n_covariates();
+        let groups = match &self.options.groups {
+            Some(groups) => groups,
+            None => return Ok(()),
+        };
+        if groups.len() != n_cov {
+            return Err(RegressionError::BadInput(format!(
+                "Expected {n_cov} group labels, found {}",
+                groups.len()
+            )));
+        }
+        if self.options.group_l > F::zero() && self.options.non_convex.is_some() {
+            return Err(RegressionError::BadInput(
+                "Group penalties cannot be combined with SCAD or MCP".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the penalty factors and L1 weights, if any, match the covariates and are
+    /// non-negative.
+    fn check_penalty_factors(&self) -> RegressionResult<()> {
+        let n_cov = self.n_covariates();
+        for factors in [&self.options.penalty_factors, &self.options.l1_weights]
+            .into_iter()
+            .flatten()
+        {
+            if factors.len() != n_cov {
+                return Err(RegressionError::BadInput(format!(
+                    "Expected {n_cov} penalty factors, found {}",
+                    factors.len()
+                )));
+            }
+            if !factors.iter().all(|&f| f >= F::zero() && f.is_finite()) {
+                return Err(RegressionError::BadInput(
+                    "Penalty factors must be finite and non-negative".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The algorithm used to solve each IRLS step under L1 or elastic net regularization. Pure L2
+/// regularization is always solved directly, and fits with bounds always use coordinate descent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Solver {
+    /// The alternating direction method of multipliers (ADMM), with an adaptive penalty
+    /// parameter.
+    #[default]
+    Admm,
+    /// Cyclic coordinate descent as in glmnet, with strong-rule screening and active-set
+    /// cycling. This usually converges in far fewer iterations than ADMM, particularly when
+    /// many parameters are zero. It converges slowly on strongly correlated covariates, and the
+    /// fit returns `RegressionError::MaxIter` if an IRLS step takes too many passes over the
+    /// coordinates.
+    CoordinateDescent,
 }
 
 /// Specifies the fitting options
+#[derive(Clone, Debug)]
 pub struct FitOptions<F>
 where
     F: Float,
@@ -57,6 +413,33 @@ where
     /// The regularization of the fit
     pub l2: F,
     pub l1: F,
+    /// The solver for L1 and elastic net regularization
+    pub solver: Solver,
+    /// The factor scaling the penalties of each covariate, not including the intercept. All
+    /// covariates are penalized equally if this is not provided.
+    pub penalty_factors: Option<Array1<F>>,
+    /// The weight scaling only the L1 penalty of each covariate, not including the intercept, as
+    /// for the adaptive lasso
+    pub l1_weights: Option<Array1<F>>,
+    /// The group lasso penalty
+    pub group_l: F,
+    /// The group label of each covariate for the group lasso, not including the intercept
+    pub groups: Option<Vec<usize>>,
+    /// A non-convex penalty replacing the L1 penalty
+    pub non_convex: Option<NonConvex<F>>,
+    /// The Tikhonov penalty matrix over the covariates, not including the intercept
+    pub penalty_matrix: Option<Array2<F>>,
+    /// The prior mean of the covariate coefficients at which the L2 and Tikhonov penalties are
+    /// centered, not including the intercept
+    pub prior_mean: Option<Array1<F>>,
+    /// The lower bound of each covariate coefficient, not including the intercept
+    pub lower_bounds: Option<Array1<F>>,
+    /// The upper bound of each covariate coefficient, not including the intercept
+    pub upper_bounds: Option<Array1<F>>,
+    /// The matrix `C` of the linear equality constraints `C * beta = d` on the parameters
+    pub constraint_mat: Option<Array2<F>>,
+    /// The vector `d` of the linear equality constraints `C * beta = d` on the parameters
+    pub constraint_vec: Option<Array1<F>>,
     /// An initial guess. A sensible default is selected if this is not provided.
     pub init_guess: Option<Array1<F>>,
 }
@@ -73,6 +456,18 @@ where
             tol: F::epsilon(),
             l2: F::zero(),
             l1: F::zero(),
+            solver: Solver::Admm,
+            penalty_factors: None,
+            l1_weights: None,
+            group_l: F::zero(),
+            groups: None,
+            non_convex: None,
+            penalty_matrix: None,
+            prior_mean: None,
+            lower_bounds: None,
+            upper_bounds: None,
+            constraint_mat: None,
+            constraint_vec: None,
             init_guess: None,
         }
     }
@@ -0,0 +1,301 @@
+//! Regularization paths, fitting a sequence of models over a grid of penalty strengths with warm
+//! starts.
+use super::options::FitConfig;
+use crate::{
+    error::{RegressionError, RegressionResult},
+    glm::Glm,
+    irls::l1_penalty_weights,
+    link::Transform,
+    model::{Dataset, Model},
+    num::Float,
+};
+use ndarray::{Array1, Array2, Axis};
+use std::{cell::RefCell, marker::PhantomData};
+
+/// The results of fitting a model at each penalty strength along a regularization path.
+#[derive(Clone, Debug)]
+pub struct RegPath<F> {
+    /// The overall penalty strength `lambda` of each fit, in decreasing order.
+    pub lambdas: Array1<F>,
+    /// The parameters of each fit, with one row per `lambda` and one column per parameter.
+    pub coefficients: Array2<F>,
+    /// The deviance of each fit, computed from the likelihood of the data without the penalty.
+    pub deviance: Array1<F>,
+    /// The effective degrees of freedom of each fit, as in `Fit::edf()`.
+    pub edf: Array1<F>,
+}
+
+impl<'a, M, F> FitConfig<'a, M, F>
+where
+    M: Glm,
+    F: 'static + Float,
+{
+    /// Returns the smallest penalty strength for which every penalized parameter is zero, which
+    /// is the largest magnitude of the score of the null model divided by `alpha` and by the
+    /// L1 penalty weight of each parameter. The penalty is mixed as in `path()`. Since no finite L2
+    /// penalty sets the parameters exactly to zero, an `alpha` below 0.001 is treated as 0.001
+    /// here as in glmnet.
+    pub fn lambda_max(&self, alpha: F) -> RegressionResult<F> {
+        check_alpha(alpha)?;
+        let weights = l1_penalty_weights(&self.options, self.model);
+        let score = self.null_score(&weights)?;
+        let max_score: F = score
+            .iter()
+            .zip(weights.iter())
+            .filter(|(_, &w)| w > F::zero())
+            .fold(F::zero(), |acc, (&s, &w)| {
+                num_traits::Float::max(acc, num_traits::Float::abs(s) / w)
+            });
+        let alpha_min = F::from(1e-3).unwrap();
+        Ok(max_score / num_traits::Float::max(alpha, alpha_min))
+    }
+
+    /// Returns `n_lambda` penalty strengths spaced logarithmically from `lambda_max()` down to
+    /// `lambda_min_ratio * lambda_max()`, with the penalty mixed as in `path()`.
+    pub fn lambda_grid(
+        &self,
+        alpha: F,
+        n_lambda: usize,
+        lambda_min_ratio: F,
+    ) -> RegressionResult<Array1<F>> {
+        if n_lambda == 0 {
+            return Err(RegressionError::BadInput(
+                "The path requires at least one lambda".to_string(),
+            ));
+        }
+        if !(lambda_min_ratio > F::zero() && lambda_min_ratio < F::one()) {
+            return Err(RegressionError::BadInput(
+                "The minimum lambda ratio must be between 0 and 1".to_string(),
+            ));
+        }
+        let lambda_max = self.lambda_max(alpha)?;
+        if n_lambda == 1 {
+            return Ok(Array1::from_elem(1, lambda_max));
+        }
+        Array1::geomspace(lambda_max, lambda_max * lambda_min_ratio, n_lambda).ok_or_else(|| {
+            RegressionError::BadInput(
+                "The maximum lambda must be positive to generate a path".to_string(),
+            )
+        })
+    }
+
+    /// Fit the model along a regularization path of `n_lambda` penalty strengths spaced
+    /// logarithmically from `lambda_max()` down to `lambda_min_ratio * lambda_max()`. The penalty
+    /// is mixed as in glmnet, with an L1 strength of `alpha * lambda` and an L2 strength of
+    /// `(1 - alpha) * lambda`, so that `alpha = 1` is the lasso and `alpha = 0` is ridge
+    /// regression. Each fit uses the other options of this configuration and is warm-started
+    /// from the result of the previous one.
+    pub fn path(
+        self,
+        alpha: F,
+        n_lambda: usize,
+        lambda_min_ratio: F,
+    ) -> RegressionResult<RegPath<F>> {
+        let lambdas = self.lambda_grid(alpha, n_lambda, lambda_min_ratio)?;
+        self.path_with_lambdas(alpha, &lambdas)
+    }
+
+    /// Fit the model along a regularization path with the given penalty strengths, which should
+    /// be in decreasing order for the warm starts to be effective. The penalty is mixed as in
+    /// `path()`.
+    pub fn path_with_lambdas(self, alpha: F, lambdas: &Array1<F>) -> RegressionResult<RegPath<F>> {
+        check_alpha(alpha)?;
+        let n_par = self.model.data.x.ncols();
+        let mut coefficients = Array2::<F>::zeros((lambdas.len(), n_par));
+        let mut deviance = Array1::<F>::zeros(lambdas.len());
+        let mut edf = Array1::<F>::zeros(lambdas.len());
+        let mut options = self.options;
+        let sat_like: F = self.model.data.y.mapv(M::log_like_sat).sum();
+        for (i, &lambda) in lambdas.iter().enumerate() {
+            options.l1 = alpha * lambda;
+            options.l2 = (F::one() - alpha) * lambda;
+            let fit = self.model.with_options(options.clone()).fit()?;
+            coefficients.row_mut(i).assign(&fit.result);
+            deviance[i] = F::from(2.).unwrap() * (sat_like - fit.data_like());
+            edf[i] = fit.edf();
+            options.init_guess = Some(fit.result);
+        }
+        Ok(RegPath {
+            lambdas: lambdas.clone(),
+            coefficients,
+            deviance,
+            edf,
+        })
+    }
+
+    /// The score of the unregularized likelihood at the null model, which fixes all penalized
+    /// parameters to zero and fits the unpenalized ones, such as the intercept.
+    fn null_score(&self, weights: &Array1<F>) -> RegressionResult<Array1<F>> {
+        let data = &self.model.data;
+        let mut null_params = Array1::<F>::zeros(data.x.ncols());
+        let unpenalized: Vec<usize> = (0..weights.len())
+            .filter(|&j| weights[j] == F::zero())
+            .collect();
+        if !unpenalized.is_empty() {
+            // Fit the unpenalized parameters alone, which accounts for any linear offsets.
+            let null_model = Model {
+                model: PhantomData::<M>,
+                data: Dataset::<F> {
+                    y: data.y.clone(),
+                    x: data.x.select(Axis(1), &unpenalized),
+                    linear_offset: data.linear_offset.clone(),
+                    weights: data.weights.clone(),
+                    hat: RefCell::new(None),
+                },
+                use_intercept: self.model.use_intercept,
+                standardization: None,
+                aliased: Vec::new(),
+            };
+            let null_fit = null_model
+                .fit_options()
+                .max_iter(self.options.max_iter)
+                .fit()?;
+            for (&j, &val) in unpenalized.iter().zip(null_fit.result.iter()) {
+                null_params[j] = val;
+            }
+        }
+        let lin_pred: Array1<F> = data.linear_predictor(&null_params);
+        let mu: Array1<F> = M::mean(&lin_pred);
+        // adjust for non-canonical link functions.
+        let eta_d = M::Link::d_nat_param(&lin_pred);
+        let resid_working: Array1<F> = eta_d * (&data.y - &mu);
+        Ok(data.x.t().dot(&resid_working))
+    }
+}
+
+/// Check that the elastic net mixing parameter is between 0 and 1.
+fn check_alpha<F: Float>(alpha: F) -> RegressionResult<()> {
+    if !(alpha >= F::zero() && alpha <= F::one()) {
+        return Err(RegressionError::BadInput(format!(
+            "The mixing parameter must be between 0 and 1, found {alpha}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::ModelBuilder, Logistic};
+    use anyhow::Result;
+    use approx::assert_abs_diff_eq;
+    use ndarray::array;
+
+    #[test]
+    fn lasso_path_logistic() -> Result<()> {
+        let data_y = array![
+            true, false, true, false, true, false, false, true, true, false, true, false, true,
+            false
+        ];
+        let data_x = array![
+            [0.5, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.2],
+            [0.4, 0.5],
+            [0.6, 0.1],
+            [0.1, 0.4],
+            [-0.2, -0.4],
+            [0.9, 0.3],
+            [0.2, -0.2],
+            [-0.1, 0.7],
+            [-0.4, -0.1],
+            [0.7, 0.2],
+            [0.3, 0.3],
+            [-0.5, 0.5]
+        ];
+        let model = ModelBuilder::<Logistic>::data(&data_y, &data_x).build()?;
+        let lambda_max = model.fit_options().lambda_max(1.)?;
+        // The score of the intercept-only model is the covariance of the covariates with the
+        // residuals from the mean.
+        let y_bar = 7. / 14.;
+        let score: Vec<f64> = (0..2)
+            .map(|j| {
+                data_x
+                    .column(j)
+                    .iter()
+                    .zip(data_y.iter())
+                    .map(|(x, &y)| x * (f64::from(u8::from(y)) - y_bar))
+                    .sum::<f64>()
+                    .abs()
+            })
+            .collect();
+        assert_abs_diff_eq!(lambda_max, score[0].max(score[1]), epsilon = 1e-10);
+
+        let path = model.fit_options().max_iter(256).path(1., 8, 0.01)?;
+        assert_eq!(path.coefficients.dim(), (8, 3));
+        assert_abs_diff_eq!(path.lambdas[0], lambda_max, epsilon = 1e-12);
+        assert_abs_diff_eq!(path.lambdas[7], 0.01 * lambda_max, epsilon = 1e-12);
+        assert_abs_diff_eq!(path.coefficients[[0, 1]], 0., epsilon = 1e-6);
+        assert_abs_diff_eq!(path.coefficients[[0, 2]], 0., epsilon = 1e-6);
+        // Only the intercept is active above lambda_max, and a covariate enters below it.
+        let edge = model
+            .fit_options()
+            .max_iter(256)
+            .path_with_lambdas(1., &array![1.1 * lambda_max, 0.9 * lambda_max])?;
+        assert_abs_diff_eq!(edge.edf[0], 1.);
+        assert_abs_diff_eq!(edge.edf[1], 2.);
+        // The deviance decreases as the penalty is relaxed.
+        for i in 1..8 {
+            assert!(path.deviance[i] <= path.deviance[i - 1] + 1e-8);
+        }
+        assert!(model.fit_options().path(1.5, 8, 0.01).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ridge_path() -> Result<()> {
+        use crate::Linear;
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![
+            [0.6, 0.2],
+            [2.1, 0.4],
+            [0.4, -0.3],
+            [-3.2, 0.1],
+            [0.7, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.5],
+            [0.5, 0.0]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let lambdas = array![10., 1., 0.1];
+        let path = model.fit_options().path_with_lambdas(0., &lambdas)?;
+        for (i, &l2) in lambdas.iter().enumerate() {
+            let fit = model.fit_options().l2_reg(l2).fit()?;
+            assert_abs_diff_eq!(path.coefficients.row(i), fit.result, epsilon = 1e-10);
+            assert_abs_diff_eq!(path.edf[i], fit.edf(), epsilon = 1e-10);
+        }
+        for i in 1..3 {
+            assert!(path.edf[i] > path.edf[i - 1]);
+        }
+        Ok(())
+    }
+
+    /// The unpenalized covariate is fit in the null model and excluded from lambda_max.
+    #[test]
+    fn path_unpenalized() -> Result<()> {
+        use crate::{Linear, Solver};
+        let data_y = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+        let data_x = array![
+            [0.6, 0.2],
+            [2.1, 0.4],
+            [0.4, -0.3],
+            [-3.2, 0.1],
+            [0.7, 0.6],
+            [0.1, -0.1],
+            [-0.3, 0.5],
+            [0.5, 0.0]
+        ];
+        let model = ModelBuilder::<Linear>::data(&data_y, &data_x).build()?;
+        let config = || {
+            model
+                .fit_options()
+                .penalty_factors(array![0., 1.])
+                .solver(Solver::CoordinateDescent)
+        };
+        let lambda_max = config().lambda_max(1.)?;
+        let edge = config().path_with_lambdas(1., &array![1.01 * lambda_max, 0.99 * lambda_max])?;
+        assert_abs_diff_eq!(edge.coefficients[[0, 2]], 0.);
+        assert!(edge.coefficients[[0, 1]] != 0.);
+        assert!(edge.coefficients[[1, 2]] != 0.);
+        Ok(())
+    }
+}
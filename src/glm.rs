@@ -11,6 +11,7 @@ use crate::{
 };
 use ndarray::{Array1, Array2};
 use ndarray_linalg::SolveH;
+use rand::Rng;
 
 /// Whether the model's response has a free dispersion parameter (e.g. linear) or if it is fixed to
 /// one (e.g. logistic)
@@ -42,6 +43,15 @@ pub trait Glm: Sized {
         lin_pred.mapv(Self::Link::func_inv)
     }
 
+    /// The derivative of the expected value with respect to the linear predictor, `d mu / d eta`.
+    /// This is the variance function, which is the derivative of the mean with respect to the
+    /// natural parameter, times the derivative of the natural parameter with respect to the
+    /// linear predictor.
+    fn d_mean<F: Float>(lin_pred: &Array1<F>) -> Array1<F> {
+        let var: Array1<F> = Self::mean(lin_pred).mapv_into(Self::variance);
+        var * Self::Link::d_nat_param(lin_pred)
+    }
+
     /// The logarithm of the partition function in terms of the natural parameter.
     /// This can be used to calculate the normalized likelihood.
     fn log_partition<F: Float>(nat_par: F) -> F;
@@ -85,6 +95,14 @@ pub trait Glm: Sized {
     where
         F: Float;
 
+    /// Draw a random response from the distribution with the given mean and dispersion
+    /// parameter. The dispersion is ignored by families in which it is fixed to one. An error is
+    /// returned if the parameters do not define a distribution.
+    fn sample<F, R>(mean: F, phi: F, rng: &mut R) -> RegressionResult<F>
+    where
+        F: Float,
+        R: Rng + ?Sized;
+
     /// Returns the log-likelihood contributions for each observable given the regressor values.
     fn log_like_terms<F>(data: &Dataset<F>, regressors: &Array1<F>) -> Array1<F>
     where
@@ -136,7 +154,7 @@ pub trait Glm: Sized {
     fn regression<F>(
         model: &Model<Self, F>,
         options: FitOptions<F>,
-    ) -> RegressionResult<Fit<Self, F>>
+    ) -> RegressionResult<Fit<'_, Self, F>>
     where
         F: Float,
         Self: Sized,
@@ -153,10 +171,6 @@ pub trait Glm: Sized {
             // TODO: Optionally track history
         }
 
-        Ok(Fit::new(
-            &model.data,
-            model.use_intercept,
-            irls,
-        ))
+        Ok(Fit::new(model, irls))
     }
 }
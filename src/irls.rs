@@ -2,10 +2,12 @@
 use crate::glm::Glm;
 use crate::link::Transform;
 use crate::model::{Dataset, Model};
-use crate::regularization::{ElasticNet, Lasso, Null, Ridge};
+use crate::regularization::{
+    CoordDescent, ElasticNet, FoldedConcave, GroupLasso, Lasso, Null, Ridge, Tikhonov,
+};
 use crate::{
     error::{RegressionError, RegressionResult},
-    fit::options::FitOptions,
+    fit::options::{FitOptions, Solver},
     num::Float,
     regularization::IrlsReg,
 };
@@ -50,7 +52,12 @@ where
 {
     pub fn new(model: &'a Model<M, F>, initial: Array1<F>, options: FitOptions<F>) -> Self {
         let data = &model.data;
-        let reg = get_reg(&options, data.x.ncols(), model.use_intercept);
+        let reg = get_reg(&options, model);
+        let initial: Array1<F> = reg.project(initial);
+        let initial: Array1<F> = match (&options.constraint_mat, &options.constraint_vec) {
+            (Some(c_mat), Some(d_vec)) => project_affine(initial, c_mat, d_vec),
+            _ => initial,
+        };
         let initial_like_data: F = M::log_like(data, &initial);
         Self {
             model: PhantomData,
@@ -145,6 +152,8 @@ where
 
 /// Represents a step in the IRLS. Holds the current guess, likelihood, and the
 /// number of steps taken this iteration.
+// The step history isn't tracked yet, so the fields are unused outside of the iteration.
+#[allow(dead_code)]
 pub struct IrlsStep<F> {
     /// The current parameter guess.
     pub guess: Array1<F>,
@@ -171,7 +180,14 @@ where
         }
 
         let (irls_mat, irls_vec) = self.irls_mat_vec();
-        let next_guess: Array1<F> = match self.reg.next_guess(&self.guess, irls_vec, irls_mat) {
+        let next_guess = match (&self.options.constraint_mat, &self.options.constraint_vec) {
+            (Some(c_mat), Some(d_vec)) => {
+                self.reg
+                    .next_guess_constrained(&self.guess, irls_vec, irls_mat, c_mat, d_vec)
+            }
+            _ => self.reg.next_guess(&self.guess, irls_vec, irls_mat),
+        };
+        let next_guess: Array1<F> = match next_guess {
             Ok(solution) => solution,
             Err(err) => return Some(Err(err)),
         };
@@ -235,7 +251,7 @@ where
         // NOTE: It's difficult to engage the step halving because it's rarely necessary, so this
         // part of the algorithm is undertested. It may be more common using L1 regularization.
         let f_step = |x: F| {
-            let b = &next_guess * x + &self.guess * (F::one() - x);
+            let b = self.reg.project(blend(&self.guess, &next_guess, x));
             // Using the real likelihood in the step finding avoids potential issues with the
             // augmentation. They should be close to equivalent at this point because the
             // regularization has reported that the internals have converged.
@@ -250,8 +266,9 @@ where
         // If step_mult == 1, that means the guess is a good one according to the un-augmented
         // regularized likelihood, so go ahead and use it.
 
-        // If the step multiplier is not zero, it found a better guess
-        let next_guess = &next_guess * step_mult + &self.guess * (F::one() - step_mult);
+        // If the step multiplier is not zero, it found a better guess. The projection keeps the
+        // parameters that the regularization fixes at a bound exactly on it.
+        let next_guess = self.reg.project(blend(&self.guess, &next_guess, step_mult));
         let next_like_data = M::log_like(self.data, &next_guess);
         let next_like = M::log_like(self.data, &next_guess) + self.reg.likelihood(&next_guess);
         let last_like = self.last_like_data + self.reg.likelihood(&self.guess);
@@ -269,6 +286,18 @@ where
     }
 }
 
+/// Interpolates between the previous and next guesses, `next * x + prev * (1 - x)`. The
+/// parameters that are unchanged by the step are kept exactly.
+fn blend<F: Float>(prev: &Array1<F>, next: &Array1<F>, x: F) -> Array1<F> {
+    ndarray::Zip::from(prev).and(next).map_collect(|&p, &n| {
+        if n == p {
+            p
+        } else {
+            n * x + p * (F::one() - x)
+        }
+    })
+}
+
 fn small_delta<F>(new: F, old: F, tol: F) -> bool
 where
     F: Float,
@@ -293,44 +322,181 @@ where
     delta2 <= (n + new2) * tol * tol
 }
 
-/// Zero the first element of the array `l` if `use_intercept == true`
-fn zero_first_maybe<F>(mut l: Array1<F>, use_intercept: bool) -> Array1<F>
+/// Project the parameters onto the affine set satisfying the linear constraints `C * beta = d`,
+/// so that the iteration starts from a feasible point. If the constraints can't be solved the
+/// parameters are returned unchanged, and the first step will satisfy them.
+fn project_affine<F: Float>(beta: Array1<F>, c_mat: &Array2<F>, d_vec: &Array1<F>) -> Array1<F> {
+    let resid: Array1<F> = c_mat.dot(&beta) - d_vec;
+    match c_mat.dot(&c_mat.t()).solveh_into(resid) {
+        Ok(mult) => beta - c_mat.t().dot(&mult),
+        Err(_) => beta,
+    }
+}
+
+/// The column of the covariate data corresponding to each parameter of the model, which is
+/// `None` for the intercept. Columns dropped as aliased have no parameter.
+fn param_covariates<M, F>(model: &Model<M, F>) -> Vec<Option<usize>>
+where
+    M: Glm,
+    F: Float,
+{
+    let start = usize::from(model.use_intercept);
+    (0..model.data.x.ncols() + model.aliased.len())
+        .filter(|j| !model.aliased.contains(j))
+        .map(|j| j.checked_sub(start))
+        .collect()
+}
+
+/// The relative weight of the penalty on each parameter. The intercept, if it is used, is not
+/// subject to regularization.
+pub(crate) fn penalty_weights<M, F>(options: &FitOptions<F>, model: &Model<M, F>) -> Array1<F>
+where
+    M: Glm,
+    F: Float,
+{
+    param_covariates(model)
+        .iter()
+        .map(|cov| match (cov, &options.penalty_factors) {
+            (None, _) => F::zero(),
+            (Some(j), Some(factors)) => factors[*j],
+            (Some(_), None) => F::one(),
+        })
+        .collect()
+}
+
+/// The relative weight of the L1 penalty on each parameter, which is the penalty weight scaled by
+/// the L1 weight of the covariate, if any.
+pub(crate) fn l1_penalty_weights<M, F>(options: &FitOptions<F>, model: &Model<M, F>) -> Array1<F>
 where
+    M: Glm,
     F: Float,
 {
-    // if an intercept term is included it should not be subject to
-    // regularization.
-    if use_intercept {
-        l[0] = F::zero();
+    let weights = penalty_weights(options, model);
+    match &options.l1_weights {
+        Some(l1_weights) => param_covariates(model)
+            .iter()
+            .zip(weights)
+            .map(|(cov, w)| cov.map_or(w, |j| w * l1_weights[j]))
+            .collect(),
+        None => weights,
     }
-    l
 }
 
-/// Generate a regularizer from the set of options
-fn get_reg<F: Float>(
+/// The lower and upper bounds of each parameter from those of the covariates. The intercept is
+/// unbounded.
+fn param_bounds<M, F>(
+    lower: &Array1<F>,
+    upper: &Array1<F>,
+    model: &Model<M, F>,
+) -> (Array1<F>, Array1<F>)
+where
+    M: Glm,
+    F: Float,
+{
+    let (lower, upper): (Vec<F>, Vec<F>) = param_covariates(model)
+        .iter()
+        .map(|cov| match cov {
+            Some(j) => (lower[*j], upper[*j]),
+            None => (F::neg_infinity(), F::infinity()),
+        })
+        .unzip();
+    (Array1::from(lower), Array1::from(upper))
+}
+
+/// The indices of the parameters in each group of the group lasso, in the order of the group
+/// labels.
+fn parameter_groups<M, F>(groups: &[usize], model: &Model<M, F>) -> Vec<Vec<usize>>
+where
+    M: Glm,
+    F: Float,
+{
+    let covariates = param_covariates(model);
+    let mut labels: Vec<usize> = groups.to_vec();
+    labels.sort_unstable();
+    labels.dedup();
+    labels
+        .iter()
+        .map(|&label| {
+            (0..covariates.len())
+                .filter(|&i| covariates[i].is_some_and(|j| groups[j] == label))
+                .collect::<Vec<usize>>()
+        })
+        .filter(|group| !group.is_empty())
+        .collect()
+}
+
+/// The Tikhonov penalty matrix and prior mean of the parameters, given the diagonal L2 penalty.
+/// The intercept, if it is used, has no penalty and a prior mean of zero.
+fn tikhonov_terms<M, F>(
     options: &FitOptions<F>,
-    n: usize,
-    use_intercept: bool,
-) -> Box<dyn IrlsReg<F>> {
-    if options.l1 < F::zero() || options.l2 < F::zero() {
+    model: &Model<M, F>,
+    l2_diag: Array1<F>,
+) -> (Array2<F>, Array1<F>)
+where
+    M: Glm,
+    F: Float,
+{
+    let covariates = param_covariates(model);
+    let mut pen_mat: Array2<F> = Array2::from_diag(&l2_diag);
+    if let Some(cov_mat) = &options.penalty_matrix {
+        for (a, cov_a) in covariates.iter().enumerate() {
+            for (b, cov_b) in covariates.iter().enumerate() {
+                if let (Some(j), Some(k)) = (cov_a, cov_b) {
+                    pen_mat[[a, b]] += cov_mat[[*j, *k]];
+                }
+            }
+        }
+    }
+    let prior: Array1<F> = covariates
+        .iter()
+        .map(|cov| match (cov, &options.prior_mean) {
+            (Some(j), Some(mean)) => mean[*j],
+            _ => F::zero(),
+        })
+        .collect();
+    (pen_mat, prior)
+}
+
+/// Generate a regularizer from the set of options
+fn get_reg<M, F>(options: &FitOptions<F>, model: &Model<M, F>) -> Box<dyn IrlsReg<F>>
+where
+    M: Glm,
+    F: Float,
+{
+    if options.l1 < F::zero() || options.l2 < F::zero() || options.group_l < F::zero() {
         eprintln!("WARNING: regularization parameters should not be negative.");
     }
     let use_l1 = options.l1 > F::zero();
     let use_l2 = options.l2 > F::zero();
-
-    if use_l1 && use_l2 {
-        let l1_diag: Array1<F> = Array1::<F>::from_elem(n, options.l1);
-        let l1_diag: Array1<F> = zero_first_maybe(l1_diag, use_intercept);
-        let l2_diag: Array1<F> = Array1::<F>::from_elem(n, options.l2);
-        let l2_diag: Array1<F> = zero_first_maybe(l2_diag, use_intercept);
+    let l1_diag: Array1<F> = l1_penalty_weights(options, model) * options.l1;
+    let l2_diag: Array1<F> = penalty_weights(options, model) * options.l2;
+
+    let groups = options
+        .groups
+        .as_ref()
+        .filter(|_| options.group_l > F::zero());
+    if let (Some(lower), Some(upper)) = (&options.lower_bounds, &options.upper_bounds) {
+        let (lower, upper) = param_bounds(lower, upper, model);
+        Box::new(CoordDescent::from_diag(l1_diag, l2_diag).with_bounds(lower, upper))
+    } else if options.penalty_matrix.is_some() || options.prior_mean.is_some() {
+        let (pen_mat, prior) = tikhonov_terms(options, model, l2_diag);
+        Box::new(Tikhonov::new(pen_mat, prior))
+    } else if let Some(groups) = groups {
+        let groups = parameter_groups(groups, model);
+        let group_l: Array1<F> = groups
+            .iter()
+            .map(|group| options.group_l * num_traits::Float::sqrt(F::from(group.len()).unwrap()))
+            .collect();
+        Box::new(GroupLasso::from_groups(l1_diag, l2_diag, groups, group_l))
+    } else if let Some(penalty) = options.non_convex.filter(|_| use_l1) {
+        Box::new(FoldedConcave::from_diag(penalty, l1_diag, l2_diag))
+    } else if use_l1 && options.solver == Solver::CoordinateDescent {
+        Box::new(CoordDescent::from_diag(l1_diag, l2_diag))
+    } else if use_l1 && use_l2 {
         Box::new(ElasticNet::from_diag(l1_diag, l2_diag))
     } else if use_l2 {
-        let l2_diag: Array1<F> = Array1::<F>::from_elem(n, options.l2);
-        let l2_diag: Array1<F> = zero_first_maybe(l2_diag, use_intercept);
         Box::new(Ridge::from_diag(l2_diag))
     } else if use_l1 {
-        let l1_diag: Array1<F> = Array1::<F>::from_elem(n, options.l1);
-        let l1_diag: Array1<F> = zero_first_maybe(l1_diag, use_intercept);
         Box::new(Lasso::from_diag(l1_diag))
     } else {
         Box::new(Null {})
@@ -87,6 +87,7 @@
 //! ```
 
 #![doc(html_root_url = "https://docs.rs/crate/ndarray-glm")]
+mod collinearity;
 pub mod error;
 mod fit;
 mod glm;
@@ -101,8 +102,22 @@ pub mod utility;
 
 // Import some common names into the top-level namespace
 pub use {
-    fit::Fit,
+    collinearity::Collinearity,
+    fit::{
+        bootstrap::Bootstrap,
+        classification::{Calibration, Classification, ConfusionMatrix, RocCurve},
+        cv::{CrossValidation, CvMeasure},
+        gof::OverdispersionTest,
+        hypothesis::HypothesisTest,
+        influence::Influence,
+        jackknife::Jackknife,
+        margins::{MarginType, MarginalEffects},
+        options::Solver,
+        path::RegPath,
+        ExpCoefficients, Fit, PredictType, Prediction, PseudoRSq,
+    },
     model::ModelBuilder,
+    regularization::NonConvex,
     response::logistic::link as logistic_link,
     response::{binomial::Binomial, linear::Linear, logistic::Logistic, poisson::Poisson},
 };
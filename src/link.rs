@@ -21,6 +21,33 @@ pub trait Link<M: Glm>: Transform {
     // scalar function and use mapv().
     fn func_inv<F: Float>(lin_pred: F) -> F;
     // fn func_inv<F: Float>(lin_pred: Array1<F>) -> Array1<F>;
+
+    /// The interpretation of the exponentiated coefficients under this link function.
+    const EXP_COEF: ExpCoefKind = ExpCoefKind::ExpCoef;
+}
+
+/// The interpretation of the exponentiated coefficients of a model, which depends on its link
+/// function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpCoefKind {
+    /// The multiplicative change in the odds per unit change in a covariate, for logit links.
+    OddsRatio,
+    /// The multiplicative change in the rate per unit change in a covariate, for log links of
+    /// count data.
+    RateRatio,
+    /// The exponentiated coefficients without a particular interpretation.
+    ExpCoef,
+}
+
+impl std::fmt::Display for ExpCoefKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Self::OddsRatio => "odds ratio",
+            Self::RateRatio => "incidence rate ratio",
+            Self::ExpCoef => "exp(coef)",
+        };
+        write!(f, "{label}")
+    }
 }
 
 pub trait Transform {
@@ -1,6 +1,6 @@
 //! Mathematical helper functions
 use crate::num::Float;
-use ndarray::Array2;
+use ndarray::{s, Array1, Array2};
 use ndarray_linalg::QRSquareInto;
 
 /// The product-logarithm function (not the W function) x * log(x). If x == 0, 0 is returned.
@@ -34,6 +34,307 @@ where
     Ok(false)
 }
 
+/// Returns the indices of the columns of the matrix that are linearly dependent on the columns
+/// before them, using a Gram-Schmidt QR decomposition with the limited pivoting of R's `lm`. Each
+/// column is orthogonalized against the retained columns, and it is aliased if the norm of the
+/// remainder is below `tol` relative to its original norm. Columns of zeros are always aliased.
+pub fn aliased_columns<F>(matrix: &Array2<F>, tol: F) -> Vec<usize>
+where
+    F: Float,
+{
+    let mut basis: Vec<Array1<F>> = Vec::new();
+    let mut aliased: Vec<usize> = Vec::new();
+    for (j, col) in matrix.columns().into_iter().enumerate() {
+        let norm = num_traits::Float::sqrt(col.dot(&col));
+        let mut resid: Array1<F> = col.to_owned();
+        // A second pass of the orthogonalization recovers the precision lost in the first.
+        for _ in 0..2 {
+            for q in &basis {
+                let proj = q.dot(&resid);
+                resid.scaled_add(-proj, q);
+            }
+        }
+        let resid_norm = num_traits::Float::sqrt(resid.dot(&resid));
+        if norm == F::zero() || resid_norm <= tol * norm {
+            aliased.push(j);
+        } else {
+            basis.push(resid / resid_norm);
+        }
+    }
+    aliased
+}
+
+/// Returns the symmetric matrix `[[A, C^T], [C, 0]]` of the Karush-Kuhn-Tucker conditions for
+/// maximizing `b^T * x - x^T * A * x / 2` subject to the linear constraints `C * x = d`. The
+/// solution of the system with right-hand side `[b, d]` gives `x` followed by the Lagrange
+/// multipliers, and the leading block of its inverse is the inverse of `A` restricted to the
+/// null space of `C`.
+pub fn kkt_matrix<F>(a: &Array2<F>, c: &Array2<F>) -> Array2<F>
+where
+    F: Float,
+{
+    let (n, m) = (a.nrows(), c.nrows());
+    let mut kkt = Array2::<F>::zeros((n + m, n + m));
+    kkt.slice_mut(s![..n, ..n]).assign(a);
+    kkt.slice_mut(s![n.., ..n]).assign(c);
+    kkt.slice_mut(s![..n, n..]).assign(&c.t());
+    kkt
+}
+
+/// The natural logarithm of the gamma function, using the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1. - x);
+    }
+    let x = x - 1.;
+    let t = x + 7.5;
+    let series: f64 = COEFFS[0]
+        + COEFFS[1..]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c / (x + (i + 1) as f64))
+            .sum::<f64>();
+    0.5 * (2. * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// The regularized upper incomplete gamma function Q(a, x) = Gamma(a, x) / Gamma(a).
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 1.;
+    }
+    let max_iter = 512;
+    let eps = f64::EPSILON;
+    let ln_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1. {
+        // The series representation of the lower function P(a, x) converges quickly here.
+        let mut term = 1. / a;
+        let mut sum = term;
+        for n in 1..max_iter {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * eps {
+                break;
+            }
+        }
+        1. - sum * ln_prefactor.exp()
+    } else {
+        // Use the continued fraction representation of Q(a, x) with the modified Lentz method.
+        let tiny = f64::MIN_POSITIVE / eps;
+        let mut b = x + 1. - a;
+        let mut c = 1. / tiny;
+        let mut d = 1. / b;
+        let mut h = d;
+        for n in 1..max_iter {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1. / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.).abs() < eps {
+                break;
+            }
+        }
+        ln_prefactor.exp() * h
+    }
+}
+
+/// The survival function (one minus the cumulative distribution) of the chi-squared
+/// distribution with `ndf` degrees of freedom, which gives the p-value of a chi-squared test
+/// statistic. With no degrees of freedom the test has nothing to reject, so the p-value is one.
+pub fn chi_sq_sf<F: Float>(x: F, ndf: F) -> F {
+    if ndf <= F::zero() {
+        return F::one();
+    }
+    let x: f64 = x.to_f64().unwrap_or(f64::NAN);
+    if x.is_nan() {
+        return F::nan();
+    }
+    F::from(gamma_q(0.5 * ndf.to_f64().unwrap(), 0.5 * x)).unwrap()
+}
+
+/// The cumulative distribution function of the standard normal distribution.
+pub fn norm_cdf(x: f64) -> f64 {
+    // erfc(z) = Q(1/2, z^2) for z >= 0
+    let tail = 0.5 * gamma_q(0.5, 0.5 * x * x);
+    if x < 0. {
+        tail
+    } else {
+        1. - tail
+    }
+}
+
+/// The quantile function (inverse CDF) of the standard normal distribution. A rational
+/// approximation is refined with a Halley step to reach close to full double precision.
+pub fn norm_quantile(p: f64) -> f64 {
+    if p <= 0. {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1. {
+        return f64::INFINITY;
+    }
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let p_low = 0.02425;
+    let x = if p < p_low {
+        let q = (-2. * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    } else if p <= 1. - p_low {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        let q = (-2. * (-p).ln_1p()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    };
+    let err = norm_cdf(x) - p;
+    let u = err * (2. * std::f64::consts::PI).sqrt() * (0.5 * x * x).exp();
+    x - u / (1. + 0.5 * x * u)
+}
+
+/// The regularized incomplete beta function I_x(a, b).
+pub fn beta_reg(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let ln_prefactor = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (-x).ln_1p();
+    // The continued fraction converges rapidly for x < (a + 1) / (a + b + 2); otherwise use the
+    // symmetry relation.
+    if x < (a + 1.) / (a + b + 2.) {
+        ln_prefactor.exp() * beta_cont_frac(a, b, x) / a
+    } else {
+        1. - ln_prefactor.exp() * beta_cont_frac(b, a, 1. - x) / b
+    }
+}
+
+/// The continued fraction for the incomplete beta function, evaluated with the modified Lentz
+/// method.
+fn beta_cont_frac(a: f64, b: f64, x: f64) -> f64 {
+    let max_iter = 512;
+    let eps = f64::EPSILON;
+    let tiny = f64::MIN_POSITIVE / eps;
+    let clip = |v: f64| if v.abs() < tiny { tiny } else { v };
+    let mut c = 1.;
+    let mut d = 1. / clip(1. - (a + b) * x / (a + 1.));
+    let mut h = d;
+    for m in 1..max_iter {
+        let m = m as f64;
+        let m2 = 2. * m;
+        // even step
+        let aa = m * (b - m) * x / ((a + m2 - 1.) * (a + m2));
+        d = 1. / clip(1. + aa * d);
+        c = clip(1. + aa / c);
+        h *= d * c;
+        // odd step
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.));
+        d = 1. / clip(1. + aa * d);
+        c = clip(1. + aa / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.).abs() < eps {
+            break;
+        }
+    }
+    h
+}
+
+/// The cumulative distribution function of Student's t distribution with `ndf` degrees of
+/// freedom.
+pub fn t_cdf(t: f64, ndf: f64) -> f64 {
+    let tail = 0.5 * beta_reg(0.5 * ndf, 0.5, ndf / (ndf + t * t));
+    if t < 0. {
+        tail
+    } else {
+        1. - tail
+    }
+}
+
+/// The quantile function of Student's t distribution with `ndf` degrees of freedom, found by
+/// bisection of the CDF.
+pub fn t_quantile(p: f64, ndf: f64) -> f64 {
+    if p <= 0. {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1. {
+        return f64::INFINITY;
+    }
+    // The distribution is symmetric, so only search in the upper half.
+    if p < 0.5 {
+        return -t_quantile(1. - p, ndf);
+    }
+    let mut lo = 0.;
+    let mut hi = 1.;
+    while t_cdf(hi, ndf) < p {
+        lo = hi;
+        hi *= 2.;
+    }
+    for _ in 0..256 {
+        let mid = 0.5 * (lo + hi);
+        if t_cdf(mid, ndf) < p {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+        if hi - lo <= f64::EPSILON * hi {
+            break;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -47,6 +348,55 @@ mod tests {
         assert_abs_diff_eq!(e, prod_log(e));
     }
 
+    #[test]
+    fn test_ln_gamma() {
+        assert_abs_diff_eq!(ln_gamma(1.), 0., epsilon = 1e-14);
+        assert_abs_diff_eq!(ln_gamma(2.), 0., epsilon = 1e-14);
+        assert_abs_diff_eq!(ln_gamma(5.), 24f64.ln(), epsilon = 1e-13);
+        assert_abs_diff_eq!(
+            ln_gamma(0.5),
+            std::f64::consts::PI.sqrt().ln(),
+            epsilon = 1e-14
+        );
+    }
+
+    #[test]
+    fn test_chi_sq_sf() {
+        // With 2 degrees of freedom the survival function is exp(-x/2).
+        for x in [0.1, 1.0, 3.5, 20.] {
+            assert_abs_diff_eq!(chi_sq_sf(x, 2.), f64::exp(-0.5 * x), epsilon = 1e-14);
+        }
+        // Reference values of the 95th percentiles
+        assert_abs_diff_eq!(chi_sq_sf(3.841458820694124, 1.), 0.05, epsilon = 1e-12);
+        assert_abs_diff_eq!(chi_sq_sf(11.070497693516351, 5.), 0.05, epsilon = 1e-12);
+        assert_abs_diff_eq!(chi_sq_sf(0., 3.), 1.);
+        assert_abs_diff_eq!(chi_sq_sf(2., 0.), 1.);
+    }
+
+    #[test]
+    fn test_normal() {
+        assert_abs_diff_eq!(norm_cdf(0.), 0.5, epsilon = 1e-15);
+        assert_abs_diff_eq!(norm_cdf(1.959963984540054), 0.975, epsilon = 1e-14);
+        assert_abs_diff_eq!(norm_cdf(-1.), 0.15865525393145707, epsilon = 1e-14);
+        for p in [1e-10, 0.01, 0.025, 0.3, 0.5, 0.8, 0.975, 0.999] {
+            assert_abs_diff_eq!(norm_cdf(norm_quantile(p)), p, epsilon = 1e-14);
+        }
+        assert_abs_diff_eq!(norm_quantile(0.975), 1.959963984540054, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_student_t() {
+        // With one degree of freedom this is the Cauchy distribution.
+        for t in [-3., -0.5, 0., 0.2, 4.] {
+            let cauchy = 0.5 + f64::atan(t) / std::f64::consts::PI;
+            assert_abs_diff_eq!(t_cdf(t, 1.), cauchy, epsilon = 1e-14);
+        }
+        assert_abs_diff_eq!(t_quantile(0.975, 10.), 2.2281388519649385, epsilon = 1e-10);
+        assert_abs_diff_eq!(t_quantile(0.05, 3.), -2.3533634348018264, epsilon = 1e-10);
+        // approaches the normal distribution
+        assert_abs_diff_eq!(t_quantile(0.975, 1e8), norm_quantile(0.975), epsilon = 1e-6);
+    }
+
     #[test]
     fn test_rank_def() {
         assert!(is_rank_deficient(array![[0., 1.]], 0.).unwrap());
@@ -1,16 +1,17 @@
 //! Collect data for and configure a model
 
 use crate::{
+    collinearity::Collinearity,
     error::{RegressionError, RegressionResult},
     fit::{self, Fit},
     glm::Glm,
-    math::is_rank_deficient,
+    math::{aliased_columns, is_rank_deficient},
     num::Float,
     response::Response,
-    utility::one_pad,
+    utility::{one_pad, Standardization},
 };
 use fit::options::{FitConfig, FitOptions};
-use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Data, Ix1, Ix2};
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix1, Ix2};
 use ndarray_linalg::InverseInto;
 use std::{
     cell::{Ref, RefCell},
@@ -54,9 +55,23 @@ where
         }
     }
 
+    /// Returns the dataset of the given rows, which may be repeated.
+    pub(crate) fn select_rows(&self, rows: &[usize]) -> Self {
+        Dataset {
+            y: self.y.select(Axis(0), rows),
+            x: self.x.select(Axis(0), rows),
+            linear_offset: self
+                .linear_offset
+                .as_ref()
+                .map(|off| off.select(Axis(0), rows)),
+            weights: self.weights.as_ref().map(|w| w.select(Axis(0), rows)),
+            hat: RefCell::new(None),
+        }
+    }
+
     /// Returns the hat matrix of the dataset of covariate data, also known as the "projection" or
     /// "influence" matrix.
-    pub fn hat(&self) -> RegressionResult<Ref<Array2<F>>> {
+    pub fn hat(&self) -> RegressionResult<Ref<'_, Array2<F>>> {
         if self.hat.borrow().is_none() {
             if self.weights.is_some() {
                 unimplemented!("Weights must be accounted for in the hat matrix")
@@ -90,6 +105,13 @@ where
     pub data: Dataset<F>,
     /// Whether the intercept term is used (commonly true)
     pub use_intercept: bool,
+    /// The standardization applied to the covariates, if the model was built to standardize them
+    /// internally.
+    pub standardization: Option<Standardization<F>>,
+    /// The indices of the columns of the full design matrix, including the intercept, that were
+    /// dropped because they are linearly dependent on earlier columns. This is empty unless the
+    /// model was built with `drop_aliased()`.
+    pub aliased: Vec<usize>,
 }
 
 impl<M, F> Model<M, F>
@@ -97,13 +119,19 @@ where
     M: Glm,
     F: Float,
 {
+    /// Returns the collinearity diagnostics of the design matrix, including the intercept and
+    /// any standardization.
+    pub fn collinearity(&self) -> RegressionResult<Collinearity<F>> {
+        Collinearity::from_design(&self.data.x, self.use_intercept)
+    }
+
     /// Perform the regression and return a fit object holding the results.
-    pub fn fit(&self) -> RegressionResult<Fit<M, F>> {
+    pub fn fit(&self) -> RegressionResult<Fit<'_, M, F>> {
         self.fit_options().fit()
     }
 
     /// Fit options builder interface
-    pub fn fit_options(&self) -> FitConfig<M, F> {
+    pub fn fit_options(&self) -> FitConfig<'_, M, F> {
         FitConfig {
             model: self,
             options: FitOptions::default(),
@@ -111,7 +139,7 @@ where
     }
 
     /// An experimental interface that would allow fit options to be set externally.
-    pub fn with_options(&self, options: FitOptions<F>) -> FitConfig<M, F> {
+    pub fn with_options(&self, options: FitOptions<F>) -> FitConfig<'_, M, F> {
         FitConfig {
             model: self,
             options,
@@ -146,7 +174,9 @@ impl<M: Glm> ModelBuilder<M> {
             linear_offset: None,
             weights: None,
             use_intercept_term: true,
+            standardize: false,
             colin_tol: F::epsilon(),
+            drop_aliased: false,
         }
     }
 }
@@ -174,8 +204,13 @@ where
     weights: Option<Array1<F>>,
     /// Whether to use an intercept term. Defaults to `true`.
     use_intercept_term: bool,
+    /// Whether to standardize the covariates internally. Defaults to `false`.
+    standardize: bool,
     /// tolerance for determinant check on rank of data matrix X.
     colin_tol: F,
+    /// Whether to drop aliased columns instead of failing the co-linearity check. Defaults to
+    /// `false`.
+    drop_aliased: bool,
 }
 
 /// A builder to generate a Model object
@@ -198,6 +233,15 @@ where
         self
     }
 
+    /// Standardize each covariate by subtracting its mean and dividing by its standard deviation
+    /// before fitting, as with `utility::standardize()`. The fit parameters are then with respect
+    /// to the standardized covariates, and the transformation is remembered so that predictions
+    /// on new data apply it automatically.
+    pub fn standardize(mut self) -> Self {
+        self.standardize = true;
+        self
+    }
+
     /// Set the tolerance for the co-linearity check.
     /// The check can be effectively disabled by setting the tolerance to a negative value.
     pub fn colinear_tol(mut self, tol: F) -> Self {
@@ -205,6 +249,46 @@ where
         self
     }
 
+    /// Drop the columns of the design matrix that are linearly dependent on earlier columns
+    /// instead of returning `ColinearData`, as R does. The aliased columns are identified by a
+    /// pivoted QR decomposition with a relative tolerance of the square root of machine epsilon,
+    /// and the intercept and earlier columns are kept in preference to later ones. Only the
+    /// identifiable parameters are fit; `Fit::coefficients()` reports the aliased ones as NaN.
+    /// New data for predictions should still include every covariate.
+    pub fn drop_aliased(mut self) -> Self {
+        self.drop_aliased = true;
+        self
+    }
+
+    /// Returns the collinearity diagnostics of the design matrix that would be built, including
+    /// the intercept and any standardization. This is available even when `build()` fails with
+    /// `ColinearData`, and `Collinearity::collinear_sets()` identifies the columns responsible.
+    pub fn collinearity(&self) -> RegressionResult<Collinearity<F>> {
+        let (_, data_x) = self.design();
+        Collinearity::from_design(&data_x, self.use_intercept_term)
+    }
+
+    /// The standardization of the covariates, if requested, and the design matrix with the
+    /// intercept column prepended if it is used.
+    fn design(&self) -> (Option<Standardization<F>>, Array2<F>) {
+        let standardization: Option<Standardization<F>> = if self.standardize {
+            Some(Standardization::from_data(&self.data_x))
+        } else {
+            None
+        };
+        let data_x: Array2<F> = match &standardization {
+            Some(stand) => stand.transform(&self.data_x),
+            None => self.data_x.to_owned(),
+        };
+        // add constant term to X data
+        let data_x = if self.use_intercept_term {
+            one_pad(data_x.view())
+        } else {
+            data_x
+        };
+        (standardization, data_x)
+    }
+
     pub fn build(self) -> RegressionResult<Model<M, F>>
     where
         M: Glm,
@@ -225,11 +309,19 @@ where
             }
         }
 
-        // add constant term to X data
-        let data_x = if self.use_intercept_term {
-            one_pad(self.data_x)
+        let (standardization, data_x) = self.design();
+        let aliased: Vec<usize> = if self.drop_aliased {
+            aliased_columns(&data_x, num_traits::Float::sqrt(F::epsilon()))
+        } else {
+            Vec::new()
+        };
+        let data_x: Array2<F> = if aliased.is_empty() {
+            data_x
         } else {
-            self.data_x.to_owned()
+            let kept: Vec<usize> = (0..data_x.ncols())
+                .filter(|j| !aliased.contains(j))
+                .collect();
+            data_x.select(Axis(1), &kept)
         };
         // Check if the data is under-constrained
         if n_data < data_x.ncols() {
@@ -239,7 +331,8 @@ where
             // return Err(RegressionError::Underconstrained);
             eprintln!("Warning: data is underconstrained");
         }
-        // Check for co-linearity up to a tolerance
+        // Check for co-linearity up to a tolerance. The columns responsible can be identified with
+        // `collinearity()`.
         let xtx: Array2<F> = data_x.t().dot(&data_x);
         if is_rank_deficient(xtx, self.colin_tol)? {
             return Err(RegressionError::ColinearData);
@@ -262,6 +355,8 @@ where
                 hat: RefCell::new(None),
             },
             use_intercept: self.use_intercept_term,
+            standardization,
+            aliased,
         })
     }
 }
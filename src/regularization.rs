@@ -1,17 +1,22 @@
 //! Regularization methods and their effect on the likelihood and the matrix and
 //! vector components of the IRLS step.
-use crate::{error::RegressionResult, num::Float, Array1, Array2};
-use ndarray::ArrayViewMut1;
-use ndarray_linalg::SolveH;
+use crate::{
+    error::{RegressionError, RegressionResult},
+    math::kkt_matrix,
+    num::Float,
+    Array1, Array2,
+};
+use ndarray::{s, ArrayViewMut1, Axis};
+use ndarray_linalg::{InverseInto, SolveH};
 
 /// Penalize the likelihood with a smooth function of the regression parameters.
 pub(crate) trait IrlsReg<F>
 where
     F: Float,
 {
-    /// Defines the impact of the regularization approach on the likelihood. It
-    /// must be zero when the regressors are zero, otherwise some assumptions in
-    /// the fitting statistics section may be invalidated.
+    /// Defines the impact of the regularization approach on the likelihood. This need not be
+    /// zero when the regressors are zero, as for a penalty centered at a prior mean, so the
+    /// likelihood of the null model includes it.
     fn likelihood(&self, regressors: &Array1<F>) -> F;
 
     /// Defines the regularization effect on the gradient of the likelihood with respect
@@ -21,6 +26,12 @@ where
     /// Processing to do before each step.
     fn prepare(&mut self, _guess: &Array1<F>) {}
 
+    /// Project the parameters onto the feasible set of any constraints. This is applied to the
+    /// initial guess so that the iteration starts from a feasible point.
+    fn project(&self, guess: Array1<F>) -> Array1<F> {
+        guess
+    }
+
     /// For ADMM, the likelihood in the IRLS step is augmented with a rho term and does not include
     /// the L1 component. Without ADMM this should return the actual un-augmented likelihood.
     fn irls_like(&self, regressors: &Array1<F>) -> F {
@@ -55,9 +66,34 @@ where
         Ok(next_guess)
     }
 
+    /// Return the next guess as in `next_guess()`, subject to the linear equality constraints
+    /// `C * beta = d`, by solving the Karush-Kuhn-Tucker system of the IRLS step.
+    fn next_guess_constrained(
+        &mut self,
+        guess: &Array1<F>,
+        irls_vec: Array1<F>,
+        irls_mat: Array2<F>,
+        c_mat: &Array2<F>,
+        d_vec: &Array1<F>,
+    ) -> RegressionResult<Array1<F>> {
+        self.prepare(guess);
+        let lhs = self.irls_mat(irls_mat, guess);
+        let rhs = self.irls_vec(irls_vec, guess);
+        let n: usize = rhs.len();
+        let kkt_rhs: Array1<F> = rhs.into_iter().chain(d_vec.iter().copied()).collect();
+        let solution = kkt_matrix(&lhs, c_mat).solveh_into(kkt_rhs)?;
+        Ok(solution.slice_move(s![..n]))
+    }
+
     fn terminate_ok(&self, _tol: F) -> bool {
         true
     }
+
+    /// The effective number of degrees of freedom of the regularized fit, given the unregularized
+    /// Fisher information at the result. Without a penalty this is the number of parameters.
+    fn edf(&self, fisher: &Array2<F>, _regressors: &Array1<F>) -> F {
+        F::from(fisher.nrows()).unwrap()
+    }
 }
 
 /// Represents a lack of regularization.
@@ -117,12 +153,61 @@ impl<F: Float> IrlsReg<F> for Ridge<F> {
         mat_diag += &self.l2_vec;
         mat
     }
+    /// The trace of the ridge hat matrix.
+    fn edf(&self, fisher: &Array2<F>, _: &Array1<F>) -> F {
+        let all: Vec<usize> = (0..fisher.nrows()).collect();
+        ridge_edf(fisher, &self.l2_vec, &all)
+    }
 }
 
-/// Penalizes the likelihood by the L1-norm of the parameters.
-pub struct Lasso<F: Float> {
-    /// The L1 parameters for each element
-    l1_vec: Array1<F>,
+/// Penalizes the likelihood by `(beta - beta_0)^T * P * (beta - beta_0) / 2` for a positive
+/// semi-definite matrix `P` and a prior mean `beta_0`, which generalizes ridge regression.
+pub struct Tikhonov<F: Float> {
+    /// The penalty matrix
+    pen_mat: Array2<F>,
+    /// The prior mean of the parameters
+    prior: Array1<F>,
+}
+
+impl<F: Float> Tikhonov<F> {
+    /// Create the regularization from the full penalty matrix and prior mean, outsourcing the
+    /// question of whether to penalize the first term (usually the intercept).
+    pub fn new(pen_mat: Array2<F>, prior: Array1<F>) -> Self {
+        Self { pen_mat, prior }
+    }
+}
+
+impl<F: Float> IrlsReg<F> for Tikhonov<F> {
+    fn likelihood(&self, beta: &Array1<F>) -> F {
+        let diff: Array1<F> = beta - &self.prior;
+        -F::from(0.5).unwrap() * diff.dot(&self.pen_mat.dot(&diff))
+    }
+    /// The gradient is penalized by `P * (beta - beta_0)`.
+    fn gradient(&self, jac: Array1<F>, beta: &Array1<F>) -> Array1<F> {
+        jac - self.pen_mat.dot(&(beta - &self.prior))
+    }
+    /// The terms in `beta` cancel as in ridge regression, leaving the prior mean.
+    fn irls_vec(&self, vec: Array1<F>, _: &Array1<F>) -> Array1<F> {
+        vec + self.pen_mat.dot(&self.prior)
+    }
+    /// Add the penalty matrix to the information matrix.
+    fn irls_mat(&self, mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+        mat + &self.pen_mat
+    }
+    /// The trace of the generalized ridge hat matrix `(I + P)^-1 * I`.
+    fn edf(&self, fisher: &Array2<F>, _: &Array1<F>) -> F {
+        match (fisher + &self.pen_mat).inv_into() {
+            Ok(inv) => (&inv * &fisher.t()).sum(),
+            Err(_) => F::from(fisher.nrows()).unwrap(),
+        }
+    }
+}
+
+/// The state of the alternating direction method of multipliers (ADMM) shared by the L1-type
+/// regularizers. The IRLS step solves for the primal parameters with an augmented quadratic term,
+/// and each regularizer sets the dual solution by the proximal operator of its penalty. The
+/// penalty parameter is adapted to balance the primal and dual residuals.
+struct Admm<F: Float> {
     /// The dual solution
     dual: Array1<F>,
     /// The cumulative sum of residuals for each element
@@ -133,59 +218,48 @@ pub struct Lasso<F: Float> {
     r_sq: F,
     /// L2-Norm of dual residuals |s|^2
     s_sq: F,
+    /// The ratio of the residuals beyond which the penalty parameter is adjusted
+    mu: F,
+    /// The factor by which the penalty parameter is adjusted
+    tau: F,
 }
 
-impl<F: Float> Lasso<F> {
-    /// Create the regularization from the diagonal, outsourcing the question of whether to include
-    /// the first term (commonly the intercept, which is left out) in the diagonal.
-    pub fn from_diag(l1: Array1<F>) -> Self {
-        let n: usize = l1.len();
-        let gamma = Array1::zeros(n);
-        let u = Array1::zeros(n);
+impl<F: Float> Admm<F> {
+    fn new(n: usize) -> Self {
         Self {
-            l1_vec: l1,
-            dual: gamma,
-            cum_res: u,
+            dual: Array1::zeros(n),
+            cum_res: Array1::zeros(n),
             rho: F::one(),
             r_sq: F::infinity(), // or should it be NaN?
             s_sq: F::infinity(),
+            mu: F::from(8.).unwrap(),
+            tau: F::from(2.).unwrap(),
         }
     }
 
     fn update_rho(&mut self) {
-        // Can these be declared const?
-        let mu: F = F::from(8.).unwrap();
-        let tau: F = F::from(2.).unwrap();
-        if self.r_sq > mu * mu * self.s_sq {
-            self.rho *= tau;
-            self.cum_res /= tau;
+        if self.r_sq > self.mu * self.mu * self.s_sq {
+            self.rho *= self.tau;
+            self.cum_res /= self.tau;
         }
-        if self.r_sq * mu * mu < self.s_sq {
-            self.rho /= tau;
-            self.cum_res *= tau;
+        if self.r_sq * self.mu * self.mu < self.s_sq {
+            self.rho /= self.tau;
+            self.cum_res *= self.tau;
         }
     }
-}
-
-impl<F: Float> IrlsReg<F> for Lasso<F> {
-    fn likelihood(&self, beta: &Array1<F>) -> F {
-        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
-    }
-
-    // This is used in the fit's score function, for instance. Thus it includes the regularization
-    // terms and not the augmented term.
-    fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
-        jac - &self.l1_vec * &regressors.mapv(F::sign)
-    }
 
-    /// Update the dual solution and the cumulative residuals.
-    fn prepare(&mut self, beta: &Array1<F>) {
+    /// Update the dual solution and the cumulative residuals, where `prox(v, rho)` is the
+    /// proximal operator of the penalty scaled by `1 / rho` evaluated at `v`.
+    fn update<P>(&mut self, beta: &Array1<F>, prox: P)
+    where
+        P: FnOnce(Array1<F>, F) -> Array1<F>,
+    {
         // Apply adaptive penalty term updating
         self.update_rho();
 
         let old_dual = self.dual.clone();
 
-        self.dual = soft_thresh(beta + &self.cum_res, &self.l1_vec / self.rho);
+        self.dual = prox(beta + &self.cum_res, self.rho);
         // the primal residuals
         let r: Array1<F> = beta - &self.dual;
         // the dual residuals
@@ -196,6 +270,7 @@ impl<F: Float> IrlsReg<F> for Lasso<F> {
         self.s_sq = s.mapv(|s| s * s).sum();
     }
 
+    /// The augmented term of the likelihood
     fn irls_like(&self, regressors: &Array1<F>) -> F {
         -F::from(0.5).unwrap()
             * self.rho
@@ -206,13 +281,13 @@ impl<F: Float> IrlsReg<F> for Lasso<F> {
 
     /// The beta term from the gradient is cancelled by the corresponding term from the Hessian.
     /// The dual and residual terms remain.
-    fn irls_vec(&self, vec: Array1<F>, _regressors: &Array1<F>) -> Array1<F> {
+    fn irls_vec(&self, vec: Array1<F>) -> Array1<F> {
         let d: Array1<F> = &self.dual - &self.cum_res;
         vec + d * self.rho
     }
 
     /// Add the constant rho to all elements of the diagonal of the Hessian.
-    fn irls_mat(&self, mut mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+    fn irls_mat(&self, mut mat: Array2<F>) -> Array2<F> {
         let mut mat_diag: ArrayViewMut1<F> = mat.diag_mut();
         mat_diag += self.rho;
         mat
@@ -228,22 +303,73 @@ impl<F: Float> IrlsReg<F> for Lasso<F> {
     }
 }
 
+/// Penalizes the likelihood by the L1-norm of the parameters.
+pub struct Lasso<F: Float> {
+    /// The L1 parameters for each element
+    l1_vec: Array1<F>,
+    /// The ADMM state
+    admm: Admm<F>,
+}
+
+impl<F: Float> Lasso<F> {
+    /// Create the regularization from the diagonal, outsourcing the question of whether to include
+    /// the first term (commonly the intercept, which is left out) in the diagonal.
+    pub fn from_diag(l1: Array1<F>) -> Self {
+        let n: usize = l1.len();
+        Self {
+            l1_vec: l1,
+            admm: Admm::new(n),
+        }
+    }
+}
+
+impl<F: Float> IrlsReg<F> for Lasso<F> {
+    fn likelihood(&self, beta: &Array1<F>) -> F {
+        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
+    }
+
+    // This is used in the fit's score function, for instance. Thus it includes the regularization
+    // terms and not the augmented term.
+    fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
+        jac - &self.l1_vec * &regressors.mapv(F::sign)
+    }
+
+    /// Update the dual solution by soft-thresholding, and the cumulative residuals.
+    fn prepare(&mut self, beta: &Array1<F>) {
+        self.admm
+            .update(beta, |v, rho| soft_thresh(v, &self.l1_vec / rho));
+    }
+
+    fn irls_like(&self, regressors: &Array1<F>) -> F {
+        self.admm.irls_like(regressors)
+    }
+
+    fn irls_vec(&self, vec: Array1<F>, _regressors: &Array1<F>) -> Array1<F> {
+        self.admm.irls_vec(vec)
+    }
+
+    fn irls_mat(&self, mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+        self.admm.irls_mat(mat)
+    }
+
+    fn terminate_ok(&self, tol: F) -> bool {
+        self.admm.terminate_ok(tol)
+    }
+
+    /// The number of active parameters, those that are not thresholded to zero.
+    fn edf(&self, _fisher: &Array2<F>, _: &Array1<F>) -> F {
+        F::from(active_set(&self.admm.dual, &self.l1_vec).len()).unwrap()
+    }
+}
+
 /// Penalizes the likelihood with both an L1-norm and L2-norm.
 pub struct ElasticNet<F: Float> {
     /// The L1 parameters for each element
     l1_vec: Array1<F>,
     /// The L2 parameters for each element
     l2_vec: Array1<F>,
-    /// The dual solution
-    dual: Array1<F>,
-    /// The cumulative sum of residuals for each element
-    cum_res: Array1<F>,
-    /// ADMM penalty parameter
-    rho: F,
-    /// L2-Norm of primal residuals |r|^2
-    r_sq: F,
-    /// L2-Norm of dual residuals |s|^2
-    s_sq: F,
+    /// The ADMM state
+    admm: Admm<F>,
 }
 
 impl<F: Float> ElasticNet<F> {
@@ -251,96 +377,627 @@ impl<F: Float> ElasticNet<F> {
     /// the first term (commonly the intercept, which is left out) in the diagonal.
     pub fn from_diag(l1: Array1<F>, l2: Array1<F>) -> Self {
         let n: usize = l1.len();
-        let gamma = Array1::zeros(n);
-        let u = Array1::zeros(n);
         Self {
             l1_vec: l1,
             l2_vec: l2,
-            dual: gamma,
-            cum_res: u,
-            rho: F::one(),
-            r_sq: F::infinity(), // or should it be NaN?
-            s_sq: F::infinity(),
+            admm: Admm::new(n),
         }
     }
+}
 
-    fn update_rho(&mut self) {
-        // Can these be declared const?
-        let mu: F = F::from(8.).unwrap();
-        let tau: F = F::from(2.).unwrap();
-        if self.r_sq > mu * mu * self.s_sq {
-            self.rho *= tau;
-            self.cum_res /= tau;
+impl<F: Float> IrlsReg<F> for ElasticNet<F> {
+    fn likelihood(&self, beta: &Array1<F>) -> F {
+        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
+            - F::from(0.5).unwrap() * (&self.l2_vec * &beta.mapv(|b| b * b)).sum()
+    }
+
+    // This is used in the fit's score function, for instance. Thus it includes the regularization
+    // terms and not the augmented term.
+    fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
+        jac - &self.l1_vec * &regressors.mapv(F::sign) - &self.l2_vec * regressors
+    }
+
+    /// Update the dual solution by soft-thresholding, and the cumulative residuals.
+    fn prepare(&mut self, beta: &Array1<F>) {
+        self.admm
+            .update(beta, |v, rho| soft_thresh(v, &self.l1_vec / rho));
+    }
+
+    fn irls_like(&self, regressors: &Array1<F>) -> F {
+        self.admm.irls_like(regressors)
+            - F::from(0.5).unwrap() * (&self.l2_vec * &regressors.mapv(|b| b * b)).sum()
+    }
+
+    fn irls_vec(&self, vec: Array1<F>, _regressors: &Array1<F>) -> Array1<F> {
+        self.admm.irls_vec(vec)
+    }
+
+    /// Add the L2 parameters and the constant rho to the diagonal of the Hessian.
+    fn irls_mat(&self, mut mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+        let mut mat_diag: ArrayViewMut1<F> = mat.diag_mut();
+        mat_diag += &self.l2_vec;
+        self.admm.irls_mat(mat)
+    }
+
+    fn terminate_ok(&self, tol: F) -> bool {
+        self.admm.terminate_ok(tol)
+    }
+
+    /// The trace of the ridge hat matrix restricted to the active parameters.
+    fn edf(&self, fisher: &Array2<F>, _: &Array1<F>) -> F {
+        ridge_edf(
+            fisher,
+            &self.l2_vec,
+            &active_set(&self.admm.dual, &self.l1_vec),
+        )
+    }
+}
+
+/// A non-convex penalty on the magnitude of each parameter, which shrinks small parameters like
+/// the L1-norm but leaves large ones unbiased.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NonConvex<F> {
+    /// The smoothly clipped absolute deviation penalty of Fan and Li, with the concavity
+    /// parameter `gamma`, which must be greater than 2. The penalty is the L1-norm up to `lambda`
+    /// and constant above `gamma * lambda`. A common choice is `gamma = 3.7`.
+    Scad(F),
+    /// The minimax concave penalty of Zhang, with the concavity parameter `gamma`, which must be
+    /// greater than 1. The rate of the penalty decreases linearly from `lambda` at zero to zero at
+    /// `gamma * lambda`. A common choice is `gamma = 3`.
+    Mcp(F),
+}
+
+impl<F: Float> NonConvex<F> {
+    /// The concavity parameter
+    pub fn gamma(&self) -> F {
+        match *self {
+            Self::Scad(gamma) | Self::Mcp(gamma) => gamma,
         }
-        if self.r_sq * mu * mu < self.s_sq {
-            self.rho /= tau;
-            self.cum_res *= tau;
+    }
+
+    /// The smallest allowed value of the concavity parameter
+    pub(crate) fn min_gamma(&self) -> F {
+        match self {
+            Self::Scad(_) => F::from(2.).unwrap(),
+            Self::Mcp(_) => F::one(),
+        }
+    }
+
+    /// The penalty on a parameter of magnitude `t` with strength `lambda`
+    fn value(&self, t: F, lambda: F) -> F {
+        let two = F::from(2.).unwrap();
+        match *self {
+            Self::Scad(gamma) => {
+                if t <= lambda {
+                    lambda * t
+                } else if t <= gamma * lambda {
+                    (two * gamma * lambda * t - t * t - lambda * lambda)
+                        / (two * (gamma - F::one()))
+                } else {
+                    lambda * lambda * (gamma + F::one()) / two
+                }
+            }
+            Self::Mcp(gamma) => {
+                if t <= gamma * lambda {
+                    lambda * t - t * t / (two * gamma)
+                } else {
+                    gamma * lambda * lambda / two
+                }
+            }
+        }
+    }
+
+    /// The derivative of the penalty with respect to the magnitude `t`
+    fn derivative(&self, t: F, lambda: F) -> F {
+        match *self {
+            Self::Scad(gamma) => {
+                if t <= lambda {
+                    lambda
+                } else {
+                    num_traits::Float::max(gamma * lambda - t, F::zero()) / (gamma - F::one())
+                }
+            }
+            Self::Mcp(gamma) => num_traits::Float::max(lambda - t / gamma, F::zero()),
         }
     }
 }
 
-impl<F: Float> IrlsReg<F> for ElasticNet<F> {
+/// Penalizes the likelihood with a non-convex function of the magnitude of each parameter, along
+/// with an optional L2-norm. Each ADMM step uses the local linear approximation of the penalty
+/// at the current guess, which is a weighted L1-norm.
+pub struct FoldedConcave<F: Float> {
+    /// The form of the penalty
+    penalty: NonConvex<F>,
+    /// The strength of the penalty for each element
+    l1_vec: Array1<F>,
+    /// The L2 parameters for each element
+    l2_vec: Array1<F>,
+    /// The L1 weights of the current local linear approximation
+    lla_vec: Array1<F>,
+    /// The ADMM state
+    admm: Admm<F>,
+}
+
+impl<F: Float> FoldedConcave<F> {
+    /// Create the regularization from the diagonals, outsourcing the question of whether to
+    /// include the first term (commonly the intercept, which is left out) in the diagonals.
+    pub fn from_diag(penalty: NonConvex<F>, l1: Array1<F>, l2: Array1<F>) -> Self {
+        let n: usize = l1.len();
+        Self {
+            penalty,
+            lla_vec: l1.clone(),
+            l1_vec: l1,
+            l2_vec: l2,
+            admm: Admm::new(n),
+        }
+    }
+}
+
+impl<F: Float> IrlsReg<F> for FoldedConcave<F> {
     fn likelihood(&self, beta: &Array1<F>) -> F {
-        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
-            -F::from(0.5).unwrap() * (&self.l2_vec * &beta.mapv(|b| b * b)).sum()
+        let penalty: F = beta
+            .iter()
+            .zip(self.l1_vec.iter())
+            .fold(F::zero(), |acc, (&b, &l)| {
+                acc + self.penalty.value(num_traits::Float::abs(b), l)
+            });
+        -penalty - F::from(0.5).unwrap() * (&self.l2_vec * &beta.mapv(|b| b * b)).sum()
     }
 
     // This is used in the fit's score function, for instance. Thus it includes the regularization
     // terms and not the augmented term.
     fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
-        jac - &self.l1_vec * &regressors.mapv(F::sign) - &self.l2_vec * regressors
+        let rate: Array1<F> = regressors
+            .iter()
+            .zip(self.l1_vec.iter())
+            .map(|(&b, &l)| self.penalty.derivative(num_traits::Float::abs(b), l) * b.sign())
+            .collect();
+        jac - rate - &self.l2_vec * regressors
     }
 
-    /// Update the dual solution and the cumulative residuals.
+    /// Update the local linear approximation at the current guess, then the dual solution by
+    /// soft-thresholding with its weights, and the cumulative residuals.
     fn prepare(&mut self, beta: &Array1<F>) {
-        // Apply adaptive penalty term updating
-        self.update_rho();
+        self.lla_vec = beta
+            .iter()
+            .zip(self.l1_vec.iter())
+            .map(|(&b, &l)| self.penalty.derivative(num_traits::Float::abs(b), l))
+            .collect();
+        self.admm
+            .update(beta, |v, rho| soft_thresh(v, &self.lla_vec / rho));
+    }
 
-        let old_dual = self.dual.clone();
-       
-        self.dual = soft_thresh(beta + &self.cum_res, &self.l1_vec / self.rho);
-        // the primal residuals
-        let r: Array1<F> = beta - &self.dual;
-        // the dual residuals
-        let s: Array1<F> = (&self.dual - old_dual) * self.rho;
-        self.cum_res += &r;
+    fn irls_like(&self, regressors: &Array1<F>) -> F {
+        self.admm.irls_like(regressors)
+            - F::from(0.5).unwrap() * (&self.l2_vec * &regressors.mapv(|b| b * b)).sum()
+    }
 
-        self.r_sq = r.mapv(|r| r * r).sum();
-        self.s_sq = s.mapv(|s| s * s).sum();
+    fn irls_vec(&self, vec: Array1<F>, _regressors: &Array1<F>) -> Array1<F> {
+        self.admm.irls_vec(vec)
+    }
+
+    /// Add the L2 parameters and the constant rho to the diagonal of the Hessian.
+    fn irls_mat(&self, mut mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+        let mut mat_diag: ArrayViewMut1<F> = mat.diag_mut();
+        mat_diag += &self.l2_vec;
+        self.admm.irls_mat(mat)
+    }
+
+    fn terminate_ok(&self, tol: F) -> bool {
+        self.admm.terminate_ok(tol)
+    }
+
+    /// The trace of the ridge hat matrix restricted to the active parameters.
+    fn edf(&self, fisher: &Array2<F>, _: &Array1<F>) -> F {
+        ridge_edf(
+            fisher,
+            &self.l2_vec,
+            &active_set(&self.admm.dual, &self.l1_vec),
+        )
+    }
+}
+
+/// Penalizes the likelihood by the L2-norm of each group of parameters, so that the parameters of
+/// a group are selected together, along with an optional L1-norm and L2-norm of each element. The
+/// element-wise L1 term gives the sparse group lasso.
+pub struct GroupLasso<F: Float> {
+    /// The L1 parameters for each element
+    l1_vec: Array1<F>,
+    /// The L2 parameters for each element
+    l2_vec: Array1<F>,
+    /// The indices of the parameters in each group
+    groups: Vec<Vec<usize>>,
+    /// The penalty on the norm of each group
+    group_l: Array1<F>,
+    /// The ADMM state
+    admm: Admm<F>,
+}
+
+impl<F: Float> GroupLasso<F> {
+    /// Create the regularization from the element-wise diagonals and the groups of parameter
+    /// indices with the penalty on each group. Parameters that are in no group, such as the
+    /// intercept, are only subject to the element-wise terms.
+    pub fn from_groups(
+        l1: Array1<F>,
+        l2: Array1<F>,
+        groups: Vec<Vec<usize>>,
+        group_l: Array1<F>,
+    ) -> Self {
+        let n: usize = l1.len();
+        Self {
+            l1_vec: l1,
+            l2_vec: l2,
+            groups,
+            group_l,
+            admm: Admm::new(n),
+        }
+    }
+
+    /// The sum of the penalized norms of the groups.
+    fn group_norm(&self, beta: &Array1<F>) -> F {
+        self.groups
+            .iter()
+            .zip(self.group_l.iter())
+            .map(|(group, &l)| l * l2_norm(beta, group))
+            .fold(F::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<F: Float> IrlsReg<F> for GroupLasso<F> {
+    fn likelihood(&self, beta: &Array1<F>) -> F {
+        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
+            - F::from(0.5).unwrap() * (&self.l2_vec * &beta.mapv(|b| b * b)).sum()
+            - self.group_norm(beta)
+    }
+
+    // This is used in the fit's score function, for instance. Thus it includes the regularization
+    // terms and not the augmented term.
+    fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
+        let mut grad = jac - &self.l1_vec * &regressors.mapv(F::sign) - &self.l2_vec * regressors;
+        for (group, &l) in self.groups.iter().zip(self.group_l.iter()) {
+            let norm = l2_norm(regressors, group);
+            if norm > F::zero() {
+                for &j in group {
+                    grad[j] -= l * regressors[j] / norm;
+                }
+            }
+        }
+        grad
+    }
+
+    /// Update the dual solution by soft-thresholding each element and then each group, and the
+    /// cumulative residuals.
+    fn prepare(&mut self, beta: &Array1<F>) {
+        self.admm.update(beta, |v, rho| {
+            group_thresh(
+                soft_thresh(v, &self.l1_vec / rho),
+                &self.groups,
+                &self.group_l / rho,
+            )
+        });
     }
 
     fn irls_like(&self, regressors: &Array1<F>) -> F {
-        -F::from(0.5).unwrap()
-            * self.rho
-            * (regressors - &self.dual + &self.cum_res)
-                .mapv(|x| x * x)
-                .sum()
-            -F::from(0.5).unwrap() * (&self.l2_vec * &regressors.mapv(|b| b * b)).sum()
+        self.admm.irls_like(regressors)
+            - F::from(0.5).unwrap() * (&self.l2_vec * &regressors.mapv(|b| b * b)).sum()
     }
 
-    /// The beta term from the gradient is cancelled by the corresponding term from the Hessian.
-    /// The dual and residual terms remain.
     fn irls_vec(&self, vec: Array1<F>, _regressors: &Array1<F>) -> Array1<F> {
-        let d: Array1<F> = &self.dual - &self.cum_res;
-        vec + d * self.rho
+        self.admm.irls_vec(vec)
     }
 
-    /// Add the constant rho to all elements of the diagonal of the Hessian.
+    /// Add the L2 parameters and the constant rho to the diagonal of the Hessian.
     fn irls_mat(&self, mut mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
         let mut mat_diag: ArrayViewMut1<F> = mat.diag_mut();
         mat_diag += &self.l2_vec;
-        mat_diag += self.rho;
-        mat
+        self.admm.irls_mat(mat)
     }
 
     fn terminate_ok(&self, tol: F) -> bool {
-        // Expressed like this, it should perhaps instead be an epsilon^2.
-        let n: usize = self.dual.len();
-        let n_sq = F::from((n as f64).sqrt()).unwrap();
-        let r_pass = self.r_sq < n_sq * tol;
-        let s_pass = self.s_sq < n_sq * tol;
-        r_pass && s_pass
+        self.admm.terminate_ok(tol)
+    }
+
+    /// The trace of the ridge hat matrix restricted to the non-zero parameters. This neglects the
+    /// shrinkage of the group norms.
+    fn edf(&self, fisher: &Array2<F>, _: &Array1<F>) -> F {
+        let active: Vec<usize> = (0..self.admm.dual.len())
+            .filter(|&j| {
+                self.admm.dual[j] != F::zero()
+                    || (self.l1_vec[j] == F::zero()
+                        && !self.groups.iter().any(|group| group.contains(&j)))
+            })
+            .collect();
+        ridge_edf(fisher, &self.l2_vec, &active)
+    }
+}
+
+/// Penalizes the likelihood with both an L1-norm and L2-norm, either of which may be zero, and
+/// solves each IRLS step by cyclic coordinate descent as in glmnet. The coordinates are screened
+/// by the strong rule and cycled over the active set, and the Karush-Kuhn-Tucker conditions are
+/// checked for the screened coordinates before each step is accepted.
+pub struct CoordDescent<F: Float> {
+    /// The L1 parameters for each element
+    l1_vec: Array1<F>,
+    /// The L2 parameters for each element
+    l2_vec: Array1<F>,
+    /// The lower bound of each element
+    lower: Array1<F>,
+    /// The upper bound of each element
+    upper: Array1<F>,
+}
+
+impl<F: Float> CoordDescent<F> {
+    /// The maximum total number of passes over the coordinates in each IRLS step.
+    const MAX_PASSES: usize = 1000;
+
+    /// Create the regularization from the diagonals, outsourcing the question of whether to
+    /// include the first term (commonly the intercept, which is left out) in the diagonals.
+    pub fn from_diag(l1: Array1<F>, l2: Array1<F>) -> Self {
+        let n: usize = l1.len();
+        Self {
+            l1_vec: l1,
+            l2_vec: l2,
+            lower: Array1::from_elem(n, F::neg_infinity()),
+            upper: Array1::from_elem(n, F::infinity()),
+        }
+    }
+
+    /// Constrain each element to lie within the lower and upper bounds, which may be infinite.
+    /// Each coordinate update is projected onto its bounds.
+    pub fn with_bounds(mut self, lower: Array1<F>, upper: Array1<F>) -> Self {
+        self.lower = lower;
+        self.upper = upper;
+        self
+    }
+
+    /// The minimizer of the objective along coordinate `j` with the others fixed, clipped to the
+    /// bounds, or `None` if the objective is flat along the coordinate.
+    fn coord_update(
+        &self,
+        j: usize,
+        mat: &Array2<F>,
+        beta: &Array1<F>,
+        grad: &Array1<F>,
+    ) -> Option<F> {
+        // The matrix is the unregularized information, so the L2 term is added here.
+        let denom = mat[[j, j]] + self.l2_vec[j];
+        if denom <= F::zero() {
+            return None;
+        }
+        let r = grad[j] + mat[[j, j]] * beta[j];
+        let shrunk = num_traits::Float::max(num_traits::Float::abs(r) - self.l1_vec[j], F::zero());
+        let next = r.sign() * shrunk / denom;
+        Some(num_traits::Float::min(
+            num_traits::Float::max(next, self.lower[j]),
+            self.upper[j],
+        ))
+    }
+
+    /// Update each coordinate in the set once, maintaining the gradient `grad = vec - mat * beta`
+    /// of the unpenalized quadratic part of the objective. Returns the largest decrease of the
+    /// objective from a single update, up to a factor of two.
+    fn cycle(
+        &self,
+        coords: &[usize],
+        mat: &Array2<F>,
+        beta: &mut Array1<F>,
+        grad: &mut Array1<F>,
+    ) -> F {
+        let mut max_decrease = F::zero();
+        for &j in coords {
+            let next = match self.coord_update(j, mat, beta, grad) {
+                Some(next) => next,
+                None => continue,
+            };
+            let delta = next - beta[j];
+            if delta != F::zero() {
+                grad.scaled_add(-delta, &mat.column(j));
+                beta[j] = next;
+                let decrease = (mat[[j, j]] + self.l2_vec[j]) * delta * delta;
+                max_decrease = num_traits::Float::max(max_decrease, decrease);
+            }
+        }
+        max_decrease
+    }
+
+    /// Run coordinate descent over the working set until the decrease of the objective in a pass
+    /// is below `threshold`, cycling over the non-zero coordinates between full passes. Each pass
+    /// is counted in `n_passes`, and an error is returned if the maximum is reached.
+    fn solve_working(
+        &self,
+        working: &[usize],
+        mat: &Array2<F>,
+        beta: &mut Array1<F>,
+        grad: &mut Array1<F>,
+        threshold: F,
+        n_passes: &mut usize,
+    ) -> RegressionResult<()> {
+        loop {
+            *n_passes += 1;
+            if *n_passes > Self::MAX_PASSES {
+                return Err(RegressionError::MaxIter(Self::MAX_PASSES));
+            }
+            if self.cycle(working, mat, beta, grad) <= threshold {
+                return Ok(());
+            }
+            let active: Vec<usize> = working
+                .iter()
+                .copied()
+                .filter(|&j| beta[j] != F::zero())
+                .collect();
+            loop {
+                *n_passes += 1;
+                if *n_passes > Self::MAX_PASSES {
+                    return Err(RegressionError::MaxIter(Self::MAX_PASSES));
+                }
+                if self.cycle(&active, mat, beta, grad) <= threshold {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<F: Float> IrlsReg<F> for CoordDescent<F> {
+    fn likelihood(&self, beta: &Array1<F>) -> F {
+        -(&self.l1_vec * beta.mapv(num_traits::Float::abs)).sum()
+            - F::from(0.5).unwrap() * (&self.l2_vec * &beta.mapv(|b| b * b)).sum()
+    }
+
+    fn gradient(&self, jac: Array1<F>, regressors: &Array1<F>) -> Array1<F> {
+        jac - &self.l1_vec * &regressors.mapv(F::sign) - &self.l2_vec * regressors
+    }
+
+    /// The vector side is not modified since the step is not solved as a linear system.
+    fn irls_vec(&self, vec: Array1<F>, _: &Array1<F>) -> Array1<F> {
+        vec
+    }
+
+    /// Add the L2 parameters to the diagonal of the information matrix.
+    fn irls_mat(&self, mut mat: Array2<F>, _: &Array1<F>) -> Array2<F> {
+        let mut mat_diag: ArrayViewMut1<F> = mat.diag_mut();
+        mat_diag += &self.l2_vec;
+        mat
+    }
+
+    /// Clip the parameters to the bounds.
+    fn project(&self, guess: Array1<F>) -> Array1<F> {
+        ndarray::Zip::from(&guess)
+            .and(&self.lower)
+            .and(&self.upper)
+            .map_collect(|&b, &lo, &hi| num_traits::Float::min(num_traits::Float::max(b, lo), hi))
+    }
+
+    /// Maximize `vec . beta - beta . mat . beta / 2` minus the penalty by coordinate descent,
+    /// starting from the current guess.
+    fn next_guess(
+        &mut self,
+        guess: &Array1<F>,
+        irls_vec: Array1<F>,
+        irls_mat: Array2<F>,
+    ) -> RegressionResult<Array1<F>> {
+        // The coordinate descent converges when the largest decrease of the objective from
+        // updating a single coordinate is negligible compared to the largest decrease from fitting
+        // a single coordinate alone, which doesn't depend on the scale of the covariates. The
+        // threshold of `eps^1.5` resolves the parameters to a relative precision of about
+        // `eps^0.75`. This is independent of the IRLS tolerance, which applies to the change in
+        // the likelihood between steps.
+        let scale: F = irls_vec
+            .iter()
+            .zip(irls_mat.diag().iter().zip(self.l2_vec.iter()))
+            .filter(|(_, (&m, &l2))| m + l2 > F::zero())
+            .fold(F::zero(), |acc, (&v, (&m, &l2))| {
+                num_traits::Float::max(acc, v * v / (m + l2))
+            });
+        let threshold = F::epsilon() * num_traits::Float::sqrt(F::epsilon()) * scale;
+        let mut n_passes: usize = 0;
+        let mut beta: Array1<F> = self.project(guess.clone());
+        let mut grad: Array1<F> = irls_vec - irls_mat.dot(&beta);
+        // The sequential strong rule, taking the penalty at which the current guess would be
+        // optimal to be the largest scaled gradient among the penalized coordinates.
+        let l1_max: F = self
+            .l1_vec
+            .fold(F::zero(), |acc, &l| num_traits::Float::max(acc, l));
+        let lambda_prev: F = grad
+            .iter()
+            .zip(self.l1_vec.iter())
+            .filter(|(_, &l)| l > F::zero())
+            .fold(F::zero(), |acc, (&g, &l)| {
+                num_traits::Float::max(acc, num_traits::Float::abs(g) * l1_max / l)
+            });
+        let two = F::from(2.).unwrap();
+        let mut working: Vec<usize> = (0..beta.len())
+            .filter(|&j| {
+                let l1 = self.l1_vec[j];
+                beta[j] != F::zero()
+                    || l1 == F::zero()
+                    || num_traits::Float::abs(grad[j]) * l1_max >= l1 * (two * l1_max - lambda_prev)
+            })
+            .collect();
+        loop {
+            self.solve_working(
+                &working,
+                &irls_mat,
+                &mut beta,
+                &mut grad,
+                threshold,
+                &mut n_passes,
+            )?;
+            // Check the optimality conditions of the coordinates left out of the working set,
+            // which would move if updated.
+            let violations: Vec<usize> = (0..beta.len())
+                .filter(|j| !working.contains(j))
+                .filter(|&j| {
+                    self.coord_update(j, &irls_mat, &beta, &grad)
+                        .is_some_and(|next| next != beta[j])
+                })
+                .collect();
+            if violations.is_empty() {
+                break;
+            }
+            working.extend(violations);
+            working.sort_unstable();
+        }
+        Ok(beta)
+    }
+
+    /// The trace of the ridge hat matrix restricted to the non-zero parameters that are not
+    /// fixed at a bound.
+    fn edf(&self, fisher: &Array2<F>, regressors: &Array1<F>) -> F {
+        let free: Vec<usize> = active_set(regressors, &self.l1_vec)
+            .into_iter()
+            .filter(|&j| regressors[j] != self.lower[j] && regressors[j] != self.upper[j])
+            .collect();
+        ridge_edf(fisher, &self.l2_vec, &free)
+    }
+}
+
+/// The indices of the parameters that are either unpenalized by the L1 term or whose
+/// (soft-thresholded) value is non-zero.
+fn active_set<F: Float>(params: &Array1<F>, l1: &Array1<F>) -> Vec<usize> {
+    params
+        .iter()
+        .zip(l1.iter())
+        .enumerate()
+        .filter(|(_, (&d, &l))| d != F::zero() || l == F::zero())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The trace of the ridge hat matrix `(I + L2)^-1 * I` for the sub-matrix of the Fisher
+/// information `I` in the active parameters. If the penalized information is singular, the
+/// number of active parameters is returned.
+fn ridge_edf<F: Float>(fisher: &Array2<F>, l2: &Array1<F>, active: &[usize]) -> F {
+    let fisher_act: Array2<F> = fisher.select(Axis(0), active).select(Axis(1), active);
+    let mut penalized: Array2<F> = fisher_act.clone();
+    let mut pen_diag: ArrayViewMut1<F> = penalized.diag_mut();
+    pen_diag += &l2.select(Axis(0), active);
+    match penalized.inv_into() {
+        Ok(inv) => (&inv * &fisher_act.t()).sum(),
+        Err(_) => F::from(active.len()).unwrap(),
+    }
+}
+
+/// The L2-norm of the elements of `x` with the given indices
+fn l2_norm<F: Float>(x: &Array1<F>, indices: &[usize]) -> F {
+    num_traits::Float::sqrt(indices.iter().fold(F::zero(), |acc, &j| acc + x[j] * x[j]))
+}
+
+/// The group soft thresholding operator, which shrinks the norm of each group of elements by the
+/// corresponding threshold, setting the whole group to zero if its norm is below it.
+fn group_thresh<F: Float>(mut x: Array1<F>, groups: &[Vec<usize>], lambda: Array1<F>) -> Array1<F> {
+    for (group, &l) in groups.iter().zip(lambda.iter()) {
+        let norm = l2_norm(&x, group);
+        let scale = if norm > l {
+            F::one() - l / norm
+        } else {
+            F::zero()
+        };
+        for &j in group {
+            x[j] *= scale;
+        }
     }
+    x
 }
 
 /// The soft thresholding operator
@@ -377,4 +1034,14 @@ mod tests {
         let output = soft_thresh(x, lambda);
         assert_abs_diff_eq!(target, output);
     }
+
+    #[test]
+    fn group_thresh_correct() {
+        let x = array![0.5, 0.3, -0.4, 0.1, -0.1];
+        let groups = vec![vec![1, 2], vec![3, 4]];
+        // The norm of the first group is 0.5, and that of the second is below its threshold.
+        let output = group_thresh(x, &groups, array![0.25, 0.2]);
+        let target = array![0.5, 0.15, -0.2, 0., 0.];
+        assert_abs_diff_eq!(target, output, epsilon = 1e-12);
+    }
 }
@@ -6,6 +6,8 @@ use crate::{
     num::Float,
     response::Response,
 };
+use rand::Rng;
+use rand_distr::Distribution;
 
 /// Use a fixed type of u16 for the domain of the binomial distribution.
 type BinDom = u16;
@@ -42,16 +44,30 @@ impl<const N: BinDom> Glm for Binomial<N> {
         let n: F = F::from(N).unwrap();
         prod_log(y) + prod_log(n - y) - prod_log(n)
     }
+
+    /// The response is the number of successes out of `N` trials, each with a probability of
+    /// `mean / N`.
+    fn sample<F, R>(mean: F, _phi: F, rng: &mut R) -> RegressionResult<F>
+    where
+        F: Float,
+        R: Rng + ?Sized,
+    {
+        let p = (mean.to_f64().unwrap() / N as f64).clamp(0., 1.);
+        let dist = rand_distr::Binomial::new(N as u64, p)
+            .map_err(|err| RegressionError::BadInput(err.to_string()))?;
+        Ok(F::from(dist.sample(rng)).unwrap())
+    }
 }
 
 pub mod link {
     use super::*;
-    use crate::link::{Canonical, Link};
+    use crate::link::{Canonical, ExpCoefKind, Link};
     use num_traits::Float;
 
     pub struct Logit {}
     impl Canonical for Logit {}
     impl<const N: BinDom> Link<Binomial<N>> for Logit {
+        const EXP_COEF: ExpCoefKind = ExpCoefKind::OddsRatio;
         fn func<F: Float>(y: F) -> F {
             let n_float: F = F::from(N).unwrap();
             Float::ln(y / (n_float - y))
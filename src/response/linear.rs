@@ -8,6 +8,8 @@ use crate::{
     response::Response,
 };
 use num_traits::ToPrimitive;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
 use std::marker::PhantomData;
 
 /// Linear regression with constant variance (Ordinary least squares).
@@ -55,6 +57,24 @@ where
         // Only for linear regression does this identity hold.
         Self::log_partition(y)
     }
+
+    /// The response is normally distributed with a variance equal to the dispersion, which must
+    /// be finite. It is undefined for a saturated fit with no residual degrees of freedom.
+    fn sample<F, R>(mean: F, phi: F, rng: &mut R) -> RegressionResult<F>
+    where
+        F: Float,
+        R: Rng + ?Sized,
+    {
+        if !(phi >= F::zero() && phi.is_finite()) {
+            return Err(RegressionError::BadInput(format!(
+                "Cannot sample a normal response with a dispersion of {phi}"
+            )));
+        }
+        let std_dev = num_traits::Float::sqrt(phi).to_f64().unwrap();
+        let dist = Normal::new(mean.to_f64().unwrap(), std_dev)
+            .map_err(|err| RegressionError::BadInput(err.to_string()))?;
+        Ok(F::from(dist.sample(rng)).unwrap())
+    }
 }
 
 pub mod link {
@@ -9,6 +9,7 @@ use crate::{
     response::Response,
 };
 use ndarray::Array1;
+use rand::Rng;
 use std::marker::PhantomData;
 
 /// Logistic regression
@@ -92,12 +93,31 @@ where
     fn log_like_sat<F: Float>(y: F) -> F {
         prod_log(y) + prod_log(F::one() - y)
     }
+
+    /// The response is a Bernoulli variable that is one with a probability equal to the mean.
+    fn sample<F, R>(mean: F, _phi: F, rng: &mut R) -> RegressionResult<F>
+    where
+        F: Float,
+        R: Rng + ?Sized,
+    {
+        let p = mean.to_f64().unwrap().clamp(0., 1.);
+        if p.is_nan() {
+            return Err(RegressionError::BadInput(
+                "Cannot sample a Bernoulli response with an undefined mean".to_string(),
+            ));
+        }
+        if rng.gen_bool(p) {
+            Ok(F::one())
+        } else {
+            Ok(F::zero())
+        }
+    }
 }
 
 pub mod link {
     //! Link functions for logistic regression
     use super::*;
-    use crate::link::{Canonical, Link, Transform};
+    use crate::link::{Canonical, ExpCoefKind, Link, Transform};
     use crate::num::Float;
 
     /// The canonical link function for logistic regression is the logit function g(p) =
@@ -105,6 +125,7 @@ pub mod link {
     pub struct Logit {}
     impl Canonical for Logit {}
     impl Link<Logistic<Logit>> for Logit {
+        const EXP_COEF: ExpCoefKind = ExpCoefKind::OddsRatio;
         fn func<F: Float>(y: F) -> F {
             num_traits::Float::ln(y / (F::one() - y))
         }
@@ -9,6 +9,8 @@ use crate::{
     response::Response,
 };
 use num_traits::{ToPrimitive, Unsigned};
+use rand::Rng;
+use rand_distr::Distribution;
 use std::marker::PhantomData;
 
 /// Poisson regression over an unsigned integer type.
@@ -54,13 +56,29 @@ where
     fn log_like_sat<F: Float>(y: F) -> F {
         prod_log(y) - y
     }
+
+    fn sample<F, R>(mean: F, _phi: F, rng: &mut R) -> RegressionResult<F>
+    where
+        F: Float,
+        R: Rng + ?Sized,
+    {
+        let lambda = mean.to_f64().unwrap();
+        // The distribution is undefined for a non-positive rate, which can only be reached in the
+        // limit of a zero mean.
+        if lambda <= 0. {
+            return Ok(F::zero());
+        }
+        let dist = rand_distr::Poisson::new(lambda)
+            .map_err(|err| RegressionError::BadInput(err.to_string()))?;
+        Ok(F::from(dist.sample(rng)).unwrap())
+    }
 }
 
 pub mod link {
     //! Link functions for Poisson regression
     use super::Poisson;
     use crate::{
-        link::{Canonical, Link},
+        link::{Canonical, ExpCoefKind, Link},
         num::Float,
     };
 
@@ -68,6 +86,7 @@ pub mod link {
     pub struct Log {}
     impl Canonical for Log {}
     impl Link<Poisson<Log>> for Log {
+        const EXP_COEF: ExpCoefKind = ExpCoefKind::RateRatio;
         fn func<F: Float>(y: F) -> F {
             num_traits::Float::ln(y)
         }
@@ -1,6 +1,6 @@
 //! utility functions for internal library use
 
-use ndarray::{concatenate, Array1, Array2, ArrayView2, Axis};
+use ndarray::{concatenate, Array1, Array2, ArrayBase, ArrayView2, Axis, Data, Ix2};
 use num_traits::{
     identities::One,
     {Float, FromPrimitive},
@@ -23,7 +23,7 @@ where
 /// has its mean subtracted and is then divided by the standard deviation.
 /// The normalization by the standard deviation is not performed if there is only 1
 /// observation, since such an operation is undefined.
-pub fn standardize<F: Float>(mut design: Array2<F>) -> Array2<F>
+pub fn standardize<F>(mut design: Array2<F>) -> Array2<F>
 where
     F: Float + FromPrimitive + std::ops::DivAssign,
 {
@@ -44,3 +44,52 @@ where
     }
     design
 }
+
+/// The shift and scale applied to each column of a design matrix by a standardization. This is
+/// retained by a model that is standardized internally so that the identical transformation can
+/// be applied to new data.
+#[derive(Clone, Debug)]
+pub struct Standardization<F> {
+    /// The mean of each column, which is subtracted.
+    pub mean: Array1<F>,
+    /// The scale each centered column is divided by. This is the population standard deviation
+    /// of the column unless that is zero or there is only one observation, in which case it is
+    /// one.
+    pub scale: Array1<F>,
+}
+
+impl<F> Standardization<F>
+where
+    F: Float + FromPrimitive,
+{
+    /// Determine the standardization from the data, consistently with `standardize()`.
+    pub fn from_data<S>(design: &ArrayBase<S, Ix2>) -> Self
+    where
+        S: Data<Elem = F>,
+    {
+        let n_obs: usize = design.nrows();
+        let mean: Array1<F> = design
+            .mean_axis(Axis(0))
+            .unwrap_or_else(|| Array1::zeros(design.ncols()));
+        let scale: Array1<F> = if n_obs >= 2 {
+            design.std_axis(Axis(0), F::zero()).mapv_into(|sig| {
+                if sig > F::zero() {
+                    sig
+                } else {
+                    F::one()
+                }
+            })
+        } else {
+            Array1::ones(design.ncols())
+        };
+        Self { mean, scale }
+    }
+
+    /// Apply the standardization to a design matrix with the same number of columns.
+    pub fn transform<S>(&self, design: &ArrayBase<S, Ix2>) -> Array2<F>
+    where
+        S: Data<Elem = F>,
+    {
+        (design - &self.mean) / &self.scale
+    }
+}
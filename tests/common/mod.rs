@@ -112,8 +112,8 @@ pub fn y_x_from_iris() -> Result<(Array1<i32>, Array2<f32>)> {
         if split_line.len() != 5 {
             return Err(anyhow!("Expected five entries in CSV"));
         }
-        for i in 0..4 {
-            let x_val: f32 = split_line[i].parse()?;
+        for x_str in &split_line[0..4] {
+            let x_val: f32 = x_str.parse()?;
             x_vec.push(x_val);
         }
         let y_parsed = match split_line[4] {
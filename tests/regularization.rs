@@ -4,8 +4,10 @@ mod common;
 use anyhow::Result;
 use approx::assert_abs_diff_eq;
 use common::{array_from_csv, y_x_from_iris};
-use ndarray::{array, Array1, Array2};
-use ndarray_glm::{utility::standardize, Linear, Logistic, ModelBuilder};
+use ndarray::{array, s, Array1, Array2};
+use ndarray_glm::{
+    error::RegressionError, utility::standardize, Linear, Logistic, ModelBuilder, Solver,
+};
 
 #[test]
 /// Test that the intercept is not affected by regularization when the dependent
@@ -102,3 +104,599 @@ fn lasso_versicolor() -> Result<()> {
     assert_abs_diff_eq!(&target, &fit.result, epsilon = 0.01);
     Ok(())
 }
+
+#[test]
+fn lasso_versicolor_coord_descent() -> Result<()> {
+    let (y_labels, x_data) = y_x_from_iris()?;
+    let x_data = standardize(x_data);
+    let y_data: Array1<bool> = y_labels.mapv(|i| i == 1);
+    let target: Array1<f32> = array_from_csv("tests/R/log_regularization/iris_versicolor_l1_1e-2.csv")?;
+    let model = ModelBuilder::<Logistic>::data(&y_data, &x_data).build()?;
+    let fit = model
+        .fit_options()
+        .l1_reg(1e-2)
+        .solver(Solver::CoordinateDescent)
+        .fit()?;
+    assert!(fit.lr_test_against(&target) >= 0.);
+    assert_abs_diff_eq!(&target, &fit.result, epsilon = 0.01);
+    // The coordinate descent solution should be at least as good as the ADMM one, in fewer
+    // iterations.
+    let fit_admm = model.fit_options().l1_reg(1e-2).fit()?;
+    assert!(fit.model_like >= fit_admm.model_like - 1e-6);
+    assert!(fit.n_iter < fit_admm.n_iter);
+    Ok(())
+}
+
+#[test]
+fn elnet_seperable_coord_descent() -> Result<()> {
+    let (y_labels, x_data) = y_x_from_iris()?;
+    let x_data = standardize(x_data);
+    let y_data: Array1<bool> = y_labels.mapv(|i| i == 0);
+    let target: Array1<f32> = array_from_csv("tests/R/log_regularization/iris_setosa_l1_l2_1e-2.csv")?;
+    let model = ModelBuilder::<Logistic>::data(&y_data, &x_data).build()?;
+    let fit = model
+        .fit_options()
+        .l1_reg(1e-2)
+        .l2_reg(1e-2)
+        .solver(Solver::CoordinateDescent)
+        .fit()?;
+    assert!(fit.lr_test_against(&target) >= 0.);
+    assert_abs_diff_eq!(&target, &fit.result, epsilon = 0.01);
+    Ok(())
+}
+
+#[test]
+/// A strong penalty sets parameters exactly to zero under coordinate descent.
+fn lasso_sparse_coord_descent() -> Result<()> {
+    let (y_data, x_data) = sparse_design();
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    let fit = model
+        .fit_options()
+        .l1_reg(0.5)
+        .solver(Solver::CoordinateDescent)
+        .fit()?;
+    let n_zero = fit.result.iter().skip(1).filter(|&&b| b == 0.).count();
+    assert!(n_zero > 0);
+    assert_abs_diff_eq!(fit.edf(), (4 - n_zero) as f64);
+    // Non-zero parameters satisfy the stationarity condition and zero ones the subgradient
+    // condition.
+    let score = fit.score(&fit.result);
+    for (j, &b) in fit.result.iter().enumerate().skip(1) {
+        if b == 0. {
+            assert!(score[j].abs() <= 0.5 + 1e-8);
+        } else {
+            assert_abs_diff_eq!(score[j], 0., epsilon = 1e-8);
+        }
+    }
+    Ok(())
+}
+
+/// The non-orthogonal design and response of `lasso_sparse_coord_descent`.
+fn sparse_design() -> (Array1<f64>, Array2<f64>) {
+    let y_data: Array1<f64> = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+    let x_data: Array2<f64> = array![
+        [0.6, 0.2, 0.1],
+        [2.1, 0.4, -0.3],
+        [0.4, -0.3, 0.2],
+        [-3.2, 0.1, 0.0],
+        [0.7, 0.6, -0.1],
+        [0.1, -0.1, 0.4],
+        [-0.3, 0.5, 0.3],
+        [0.5, 0.0, -0.2]
+    ];
+    (y_data, x_data)
+}
+
+/// The gradient of the unpenalized linear likelihood with respect to the parameters, including
+/// the intercept.
+fn linear_score(y_data: &Array1<f64>, x_data: &Array2<f64>, beta: &Array1<f64>) -> Array1<f64> {
+    let x_full = ndarray_glm::utility::one_pad(x_data.view());
+    x_full.t().dot(&(y_data - &x_full.dot(beta)))
+}
+
+/// A design of correlated covariates on very different scales, with a response depending on a
+/// few of them. The correlation grows with `corr`.
+fn correlated_design(corr: f64) -> (Array1<f64>, Array2<f64>) {
+    let (n, p) = (200, 20);
+    // Deterministic pseudo-random noise
+    let noise = |i: usize, j: usize| {
+        ((12.9898 * i as f64 + 78.233 * j as f64).sin() * 43758.5453).fract() * 3.
+    };
+    let x_data = Array2::from_shape_fn((n, p), |(i, j)| {
+        10f64.powi(j as i32 % 4 - 1) * (corr * noise(i, p) + noise(i, j))
+    });
+    let y_data: Array1<f64> = (0..n)
+        .map(|i| x_data[[i, 0]] - 0.5 * x_data[[i, 3]] + 0.1 * x_data[[i, 5]] + noise(i, p + 1))
+        .collect();
+    (y_data, x_data)
+}
+
+#[test]
+/// Coordinate descent converges on correlated covariates that are not standardized, and it
+/// gives up after a bounded number of passes when they are nearly collinear.
+fn coord_descent_correlated() -> Result<()> {
+    let l1 = 0.5;
+    let (y_data, x_data) = correlated_design(1.);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    let fit = model
+        .fit_options()
+        .l1_reg(l1)
+        .solver(Solver::CoordinateDescent)
+        .fit()?;
+    let score = fit.score(&fit.result);
+    for (j, &b) in fit.result.iter().enumerate().skip(1) {
+        // The score scales with the covariate.
+        let tol = 1e-6 * x_data.column(j - 1).mapv(|x| x * x).sum().sqrt();
+        if b == 0. {
+            assert!(score[j].abs() <= l1 + tol);
+        } else {
+            assert_abs_diff_eq!(score[j], 0., epsilon = tol);
+        }
+    }
+
+    let (y_data, x_data) = correlated_design(20.);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    let result = model
+        .fit_options()
+        .l1_reg(0.01)
+        .solver(Solver::CoordinateDescent)
+        .fit();
+    assert!(matches!(result, Ok(_) | Err(RegressionError::MaxIter(_))));
+    Ok(())
+}
+
+#[test]
+/// A covariate with a penalty factor of zero is not shrunk.
+fn unpenalized_covariate() -> Result<()> {
+    let y_data: Array1<f64> = array![0.3, -0.2, 0.5, 0.7, 0.2, 1.4, 1.1, 0.2];
+    let x_data: Array2<f64> = array![
+        [0.6, 0.2, 0.1],
+        [2.1, 0.4, -0.3],
+        [0.4, -0.3, 0.2],
+        [-3.2, 0.1, 0.0],
+        [0.7, 0.6, -0.1],
+        [0.1, -0.1, 0.4],
+        [-0.3, 0.5, 0.3],
+        [0.5, 0.0, -0.2]
+    ];
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    // A penalty this strong zeroes the penalized covariates, leaving the regression on the
+    // unpenalized one.
+    let fit = model
+        .fit_options()
+        .l1_reg(100.)
+        .penalty_factors(array![0., 1., 1.])
+        .solver(Solver::CoordinateDescent)
+        .fit()?;
+    assert_eq!(fit.result[2], 0.);
+    assert_eq!(fit.result[3], 0.);
+    let x_0: Array2<f64> = x_data.slice(s![.., ..1]).to_owned();
+    let model_0 = ModelBuilder::<Linear>::data(&y_data, &x_0).build()?;
+    let fit_0 = model_0.fit()?;
+    assert_abs_diff_eq!(fit.result.slice(s![..2]), fit_0.result, epsilon = 1e-10);
+
+    // Uniform factors are equivalent to scaling the penalty.
+    let fit_scaled = model
+        .fit_options()
+        .l2_reg(0.5)
+        .penalty_factors(array![2., 2., 2.])
+        .fit()?;
+    let fit_ridge = model.fit_options().l2_reg(1.).fit()?;
+    assert_abs_diff_eq!(fit_scaled.result, fit_ridge.result, epsilon = 1e-10);
+
+    assert!(model
+        .fit_options()
+        .penalty_factors(array![1., 1.])
+        .fit()
+        .is_err());
+    assert!(model
+        .fit_options()
+        .penalty_factors(array![1., -1., 1.])
+        .fit()
+        .is_err());
+    Ok(())
+}
+
+/// An orthonormal design with 8 observations of 6 covariates, from the columns of a
+/// Sylvester-Hadamard matrix excluding the constant one. The penalized linear regressions on it
+/// have closed-form solutions in terms of the least-squares coefficients.
+fn orthonormal_design() -> Array2<f64> {
+    Array2::from_shape_fn((8, 6), |(i, j)| {
+        let sign = if (i & (j + 1)).count_ones() % 2 == 0 {
+            1.
+        } else {
+            -1.
+        };
+        sign / 8f64.sqrt()
+    })
+}
+
+#[test]
+/// With an orthonormal design the group lasso has a closed-form solution, shrinking the norm of
+/// each group of least-squares coefficients.
+fn group_lasso_orthonormal() -> Result<()> {
+    let x_data = orthonormal_design();
+    assert_abs_diff_eq!(x_data.t().dot(&x_data), Array2::eye(6), epsilon = 1e-12);
+    let y_data: Array1<f64> = array![1.2, -0.3, 2.1, 0.8, -1.5, 0.4, 1.9, -0.6];
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data)
+        .no_constant()
+        .build()?;
+    let groups = vec![0, 0, 1, 1, 1, 2];
+    let z: Array1<f64> = x_data.t().dot(&y_data);
+    let group_idx: [&[usize]; 3] = [&[0, 1], &[2, 3, 4], &[5]];
+    // The least-squares solution shrunk group-wise
+    let expected = |z: Array1<f64>, lambda: f64| -> Array1<f64> {
+        let mut beta = z;
+        for idx in group_idx {
+            let norm = idx.iter().map(|&j| beta[j] * beta[j]).sum::<f64>().sqrt();
+            let thresh = lambda * (idx.len() as f64).sqrt();
+            let scale = (1. - thresh / norm).max(0.);
+            idx.iter().for_each(|&j| beta[j] *= scale);
+        }
+        beta
+    };
+    let lambda = 0.5;
+    let fit = model
+        .fit_options()
+        .max_iter(256)
+        .group_reg(lambda, groups.clone())
+        .fit()?;
+    let target = expected(z.clone(), lambda);
+    // Some groups are removed entirely while others remain.
+    assert!(target.iter().any(|&b| b == 0.) && target.iter().any(|&b| b != 0.));
+    assert_abs_diff_eq!(fit.result, target, epsilon = 1e-6);
+
+    // With an additional L1 term each element is soft-thresholded first.
+    let l1 = 0.2;
+    let fit_sparse = model
+        .fit_options()
+        .max_iter(256)
+        .l1_reg(l1)
+        .group_reg(lambda, groups)
+        .fit()?;
+    let z_soft: Array1<f64> = z.mapv(|z| z.signum() * (z.abs() - l1).max(0.));
+    assert_abs_diff_eq!(fit_sparse.result, expected(z_soft, lambda), epsilon = 1e-6);
+
+    assert!(model
+        .fit_options()
+        .group_reg(lambda, vec![0, 1])
+        .fit()
+        .is_err());
+    // A non-convex penalty would otherwise be ignored by the group lasso.
+    assert!(model
+        .fit_options()
+        .l1_reg(l1)
+        .group_reg(lambda, vec![0, 0, 1, 1, 2, 2])
+        .scad(3.7)
+        .fit()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+/// With an orthonormal design the SCAD and MCP estimates are closed-form thresholdings of the
+/// least-squares coefficients, which leave the large ones unbiased.
+fn non_convex_orthonormal() -> Result<()> {
+    let x_data = orthonormal_design();
+    // The least-squares coefficients span each region of the penalties.
+    let z: Array1<f64> = array![0.3, -0.8, 1.2, -1.4, 2.5, 0.05];
+    let y_data: Array1<f64> = x_data.dot(&z);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data)
+        .no_constant()
+        .build()?;
+    let (lambda, gamma) = (0.5, 3.);
+    let soft = |z: f64| z.signum() * (z.abs() - lambda).max(0.);
+
+    let scad_target: Array1<f64> = z.mapv(|z| {
+        if z.abs() <= 2. * lambda {
+            soft(z)
+        } else if z.abs() <= gamma * lambda {
+            ((gamma - 1.) * z - z.signum() * gamma * lambda) / (gamma - 2.)
+        } else {
+            z
+        }
+    });
+    let fit_scad = model
+        .fit_options()
+        .max_iter(256)
+        .l1_reg(lambda)
+        .scad(gamma)
+        .fit()?;
+    assert_abs_diff_eq!(fit_scad.result, scad_target, epsilon = 1e-6);
+
+    let mcp_target: Array1<f64> = z.mapv(|z| {
+        if z.abs() <= gamma * lambda {
+            soft(z) / (1. - 1. / gamma)
+        } else {
+            z
+        }
+    });
+    let fit_mcp = model
+        .fit_options()
+        .max_iter(256)
+        .l1_reg(lambda)
+        .mcp(gamma)
+        .fit()?;
+    assert_abs_diff_eq!(fit_mcp.result, mcp_target, epsilon = 1e-6);
+    // The largest coefficient is not shrunk, unlike with the lasso.
+    assert_abs_diff_eq!(fit_mcp.result[4], z[4], epsilon = 1e-6);
+
+    // The penalties apply along a regularization path, which starts with all parameters at zero.
+    let path = model
+        .fit_options()
+        .max_iter(256)
+        .mcp(gamma)
+        .path(1., 4, 0.1)?;
+    assert_abs_diff_eq!(path.coefficients.row(0), Array1::zeros(6), epsilon = 1e-6);
+    assert_abs_diff_eq!(path.coefficients[[3, 4]], z[4], epsilon = 1e-6);
+
+    assert!(model.fit_options().l1_reg(lambda).scad(1.5).fit().is_err());
+    assert!(model.fit_options().l1_reg(lambda).mcp(1.).fit().is_err());
+    assert!(model
+        .fit_options()
+        .l1_reg(lambda)
+        .solver(Solver::CoordinateDescent)
+        .mcp(3.)
+        .fit()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+/// On a non-orthogonal design the SCAD and MCP estimates satisfy the stationarity condition with
+/// the derivative of the penalty, and the zero ones the subgradient condition.
+fn non_convex_sparse() -> Result<()> {
+    let (y_data, x_data) = sparse_design();
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    let (lambda, gamma) = (0.3, 3.);
+    let scad_deriv = |t: f64| {
+        if t <= lambda {
+            lambda
+        } else {
+            (gamma * lambda - t).max(0.) / (gamma - 1.)
+        }
+    };
+    let mcp_deriv = |t: f64| (lambda - t / gamma).max(0.);
+    let fit_scad = model
+        .fit_options()
+        .max_iter(256)
+        .l1_reg(lambda)
+        .scad(gamma)
+        .fit()?;
+    let fit_mcp = model
+        .fit_options()
+        .max_iter(256)
+        .l1_reg(lambda)
+        .mcp(gamma)
+        .fit()?;
+    for (fit, deriv) in [
+        (fit_scad, &scad_deriv as &dyn Fn(f64) -> f64),
+        (fit_mcp, &mcp_deriv),
+    ] {
+        let score = linear_score(&y_data, &x_data, &fit.result);
+        assert_abs_diff_eq!(score[0], 0., epsilon = 1e-5);
+        for (j, &b) in fit.result.iter().enumerate().skip(1) {
+            if b.abs() < 1e-6 {
+                assert!(score[j].abs() <= lambda + 1e-5);
+            } else {
+                assert_abs_diff_eq!(score[j], deriv(b.abs()) * b.signum(), epsilon = 1e-5);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+/// With an orthonormal design the adaptive lasso soft-thresholds each least-squares coefficient
+/// by the penalty divided by a power of its magnitude.
+fn adaptive_lasso_orthonormal() -> Result<()> {
+    let x_data = orthonormal_design();
+    let z: Array1<f64> = array![0.3, -0.8, 1.2, -1.4, 2.5, 0.05];
+    let y_data: Array1<f64> = x_data.dot(&z);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data)
+        .no_constant()
+        .build()?;
+    let (lambda, gamma) = (0.5, 1.);
+    let config = model
+        .fit_options()
+        .max_iter(256)
+        .adaptive_lasso(gamma, 0.)?;
+    let weights = config.options.l1_weights.clone().unwrap();
+    assert_abs_diff_eq!(weights, z.mapv(|z| 1. / z.abs()), epsilon = 1e-8);
+    assert!(config.options.penalty_factors.is_none());
+    let fit = model
+        .with_options(config.options.clone())
+        .l1_reg(lambda)
+        .fit()?;
+    let target: Array1<f64> =
+        z.mapv(|z| z.signum() * (z.abs() - lambda / z.abs().powf(gamma)).max(0.));
+    assert_abs_diff_eq!(fit.result, target, epsilon = 1e-6);
+
+    // The weights apply only to the L1 part of an elastic net, so the ridge shrinkage is the same
+    // for every coefficient.
+    let l2 = 0.5;
+    let elnet_fit = config.l1_reg(lambda).l2_reg(l2).fit()?;
+    assert_abs_diff_eq!(elnet_fit.result, target / (1. + l2), epsilon = 1e-6);
+
+    // The initial fit may be a ridge regression, which shrinks each coefficient by a factor.
+    let l2 = 0.25;
+    let ridge_config = model.fit_options().adaptive_lasso(2., l2)?;
+    let ridge_weights = ridge_config.options.l1_weights.unwrap();
+    assert_abs_diff_eq!(
+        ridge_weights,
+        z.mapv(|z| ((1. + l2) / z).powi(2)),
+        epsilon = 1e-8
+    );
+
+    assert!(model.fit_options().adaptive_lasso(0., 0.).is_err());
+    assert!(model.fit_options().adaptive_lasso(1., -1.).is_err());
+    Ok(())
+}
+
+#[test]
+/// Tikhonov penalties with a general matrix and a prior mean
+fn tikhonov_orthonormal() -> Result<()> {
+    let x_data = orthonormal_design();
+    let z: Array1<f64> = array![0.3, -0.8, 1.2, -1.4, 2.5, 0.05];
+    let y_data: Array1<f64> = x_data.dot(&z);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data)
+        .no_constant()
+        .build()?;
+
+    // A ridge penalty centered at a prior mean shrinks the coefficients towards it.
+    let l2 = 0.5;
+    let prior: Array1<f64> = array![0.5, 0.5, 1., -1., 2., 0.];
+    let fit_prior = model
+        .fit_options()
+        .l2_reg(l2)
+        .prior_mean(prior.clone())
+        .fit()?;
+    assert_abs_diff_eq!(
+        fit_prior.result,
+        (&z + &(&prior * l2)) / (1. + l2),
+        epsilon = 1e-10
+    );
+    // The null model is penalized by its distance from the prior mean.
+    let fit_plain = model.fit()?;
+    assert_abs_diff_eq!(
+        fit_prior.null_like(),
+        fit_plain.null_like() - 0.5 * l2 * prior.dot(&prior),
+        epsilon = 1e-10
+    );
+
+    // A multiple of the identity is equivalent to ridge regression.
+    let fit_eye = model
+        .fit_options()
+        .penalty_matrix(Array2::eye(6) * l2)
+        .fit()?;
+    let fit_ridge = model.fit_options().l2_reg(l2).fit()?;
+    assert_abs_diff_eq!(fit_eye.result, fit_ridge.result, epsilon = 1e-10);
+    assert_abs_diff_eq!(fit_eye.edf(), fit_ridge.edf(), epsilon = 1e-10);
+
+    // A roughness penalty on the first differences of the coefficients
+    let diff: Array2<f64> = Array2::from_shape_fn((5, 6), |(i, j)| {
+        if j == i {
+            -1.
+        } else if j == i + 1 {
+            1.
+        } else {
+            0.
+        }
+    });
+    let pen_mat: Array2<f64> = diff.t().dot(&diff) * 2.;
+    let fit_smooth = model.fit_options().penalty_matrix(pen_mat.clone()).fit()?;
+    let beta = &fit_smooth.result;
+    assert_abs_diff_eq!(beta + &pen_mat.dot(beta), z, epsilon = 1e-10);
+    // The penalty doesn't affect a constant shift of the coefficients.
+    let fit_shift = model
+        .fit_options()
+        .penalty_matrix(pen_mat)
+        .prior_mean(Array1::ones(6))
+        .fit()?;
+    assert_abs_diff_eq!(fit_shift.result, fit_smooth.result, epsilon = 1e-10);
+
+    assert!(model
+        .fit_options()
+        .l1_reg(0.1)
+        .prior_mean(prior)
+        .fit()
+        .is_err());
+    assert!(model
+        .fit_options()
+        .penalty_matrix(Array2::eye(5))
+        .fit()
+        .is_err());
+    let mut asym: Array2<f64> = Array2::eye(6);
+    asym[[0, 1]] = 0.5;
+    assert!(model.fit_options().penalty_matrix(asym).fit().is_err());
+    Ok(())
+}
+
+#[test]
+/// With an orthonormal design the bounded least-squares coefficients are clipped to the bounds.
+fn bounds_orthonormal() -> Result<()> {
+    let x_data = orthonormal_design();
+    let z: Array1<f64> = array![0.3, -0.8, 1.2, -1.4, 2.5, 0.05];
+    let y_data: Array1<f64> = x_data.dot(&z);
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data)
+        .no_constant()
+        .build()?;
+    let inf = f64::INFINITY;
+    let lower: Array1<f64> = array![0., 0., -inf, -1., 1., 0.1];
+    let upper: Array1<f64> = array![inf, inf, 1., inf, 2., 0.1];
+    let fit = model
+        .fit_options()
+        .bounds(lower.clone(), upper.clone())
+        .fit()?;
+    let clip = |z: &Array1<f64>| -> Array1<f64> {
+        ndarray::Zip::from(z)
+            .and(&lower)
+            .and(&upper)
+            .map_collect(|&z, &lo, &hi| z.max(lo).min(hi))
+    };
+    assert_abs_diff_eq!(fit.result, clip(&z), epsilon = 1e-10);
+    assert_eq!(fit.active_bounds(), vec![1, 2, 3, 4, 5]);
+
+    // With an L1 penalty the soft-thresholded coefficients are clipped.
+    let l1 = 0.4;
+    let fit_l1 = model
+        .fit_options()
+        .l1_reg(l1)
+        .bounds(lower.clone(), upper.clone())
+        .fit()?;
+    let z_soft: Array1<f64> = z.mapv(|z| z.signum() * (z.abs() - l1).max(0.));
+    assert_abs_diff_eq!(fit_l1.result, clip(&z_soft), epsilon = 1e-10);
+
+    let fit_nn = model.fit_options().non_negative().fit()?;
+    assert_abs_diff_eq!(fit_nn.result, z.mapv(|z| z.max(0.)), epsilon = 1e-10);
+    assert_eq!(fit_nn.active_bounds(), vec![1, 3]);
+
+    assert!(model
+        .fit_options()
+        .bounds(upper.clone(), lower.clone())
+        .fit()
+        .is_err());
+    assert!(model
+        .fit_options()
+        .bounds(array![0.], array![1.])
+        .fit()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+/// On a non-orthogonal design the bounded lasso estimates satisfy the Karush-Kuhn-Tucker
+/// conditions: the penalized score vanishes for the free parameters and points out of the
+/// feasible set for those fixed at a bound.
+fn bounds_sparse() -> Result<()> {
+    let (y_data, x_data) = sparse_design();
+    let model = ModelBuilder::<Linear>::data(&y_data, &x_data).build()?;
+    let l1 = 0.1;
+    let inf = f64::INFINITY;
+    let lower: Array1<f64> = array![0., -inf, -inf];
+    let upper: Array1<f64> = array![inf, inf, 1.];
+    let fit = model
+        .fit_options()
+        .l1_reg(l1)
+        .bounds(lower.clone(), upper.clone())
+        .fit()?;
+    assert_eq!(fit.active_bounds(), vec![1, 3]);
+    assert_abs_diff_eq!(fit.result[1], 0.);
+    assert_abs_diff_eq!(fit.result[3], 1.);
+    let score = linear_score(&y_data, &x_data, &fit.result);
+    assert_abs_diff_eq!(score[0], 0., epsilon = 1e-8);
+    for j in 1..4 {
+        let (b, lo, hi) = (fit.result[j], lower[j - 1], upper[j - 1]);
+        // The one-sided derivatives of the penalized likelihood for increasing and decreasing
+        // the parameter. Only the directions into the feasible set must not increase it.
+        let up = score[j] - l1 * if b < 0. { -1. } else { 1. };
+        let down = score[j] - l1 * if b > 0. { 1. } else { -1. };
+        if b > lo {
+            assert!(down >= -1e-8);
+        }
+        if b < hi {
+            assert!(up <= 1e-8);
+        }
+    }
+    Ok(())
+}